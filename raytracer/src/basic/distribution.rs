@@ -0,0 +1,124 @@
+// Piecewise-constant distributions, used to importance sample tabulated functions.
+
+#[derive(Clone)]
+pub struct Distribution1D {
+    func: Vec<f64>,
+    cdf: Vec<f64>,
+    func_int: f64,
+}
+
+impl Distribution1D {
+    pub fn new(func: &[f64]) -> Self {
+        let n = func.len();
+        let func: Vec<f64> = func.iter().map(|f| f.max(0.)).collect();
+        let mut cdf = vec![0.; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as f64;
+        }
+        let func_int = cdf[n];
+        if func_int == 0. {
+            for (i, c) in cdf.iter_mut().enumerate() {
+                *c = i as f64 / n as f64;
+            }
+        } else {
+            for c in cdf.iter_mut() {
+                *c /= func_int;
+            }
+        }
+        Self {
+            func,
+            cdf,
+            func_int,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.func_int
+    }
+
+    fn find_interval(&self, u: f64) -> usize {
+        // Last index i with cdf[i] <= u
+        let mut lo = 0;
+        let mut hi = self.count();
+        while lo + 1 < hi {
+            let mid = (lo + hi) / 2;
+            if self.cdf[mid] <= u {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        lo
+    }
+
+    // Returns the sampled position in [0, 1), its pdf and the index of the segment.
+    pub fn sample_continuous(&self, u: f64) -> (f64, f64, usize) {
+        let offset = self.find_interval(u);
+        let mut du = u - self.cdf[offset];
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        if width > 0. {
+            du /= width;
+        }
+        let pdf = if self.func_int > 0. {
+            self.func[offset] / self.func_int
+        } else {
+            1.
+        };
+        ((offset as f64 + du) / self.count() as f64, pdf, offset)
+    }
+
+    // Returns the sampled index and its probability.
+    pub fn sample_discrete(&self, u: f64) -> (usize, f64) {
+        let offset = self.find_interval(u);
+        (offset, self.discrete_pdf(offset))
+    }
+
+    pub fn discrete_pdf(&self, index: usize) -> f64 {
+        self.cdf[index + 1] - self.cdf[index]
+    }
+}
+
+#[derive(Clone)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    // `func` is laid out row by row, `nu` values per row and `nv` rows.
+    pub fn new(func: &[f64], nu: usize, nv: usize) -> Self {
+        let conditional: Vec<Distribution1D> = (0..nv)
+            .map(|v| Distribution1D::new(&func[v * nu..(v + 1) * nu]))
+            .collect();
+        let marginal_func: Vec<f64> = conditional.iter().map(|d| d.integral()).collect();
+        Self {
+            conditional,
+            marginal: Distribution1D::new(&marginal_func),
+        }
+    }
+
+    pub fn sample_continuous(&self, u0: f64, u1: f64) -> (f64, f64, f64) {
+        let (v, pdf1, row) = self.marginal.sample_continuous(u1);
+        let (u, pdf0, _) = self.conditional[row].sample_continuous(u0);
+        (u, v, pdf0 * pdf1)
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.marginal.integral()
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let nu = self.conditional[0].count();
+        let nv = self.marginal.count();
+        let iu = ((u * nu as f64) as usize).min(nu - 1);
+        let iv = ((v * nv as f64) as usize).min(nv - 1);
+        if self.marginal.integral() == 0. {
+            return 1.;
+        }
+        self.conditional[iv].func[iu] / self.marginal.integral()
+    }
+}
@@ -0,0 +1,175 @@
+use std::ops::{Add, Index, Mul, Sub};
+
+use crate::basic::ray::Ray;
+use crate::basic::vec::Vec3;
+use crate::hittable::bvh::aabb::AABB;
+
+// Scalar in which mesh vertices and BVH nodes are stored, and rays are tested
+// against them. The `f32` feature halves their memory, shading and accumulation
+// stay in f64 either way.
+#[cfg(feature = "f32")]
+pub type Float = f32;
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+
+// Bound on the relative error of `n` operations in `Float`, as in pbrt.
+pub fn gamma(n: i32) -> f64 {
+    let e = Float::EPSILON as f64 * 0.5;
+    n as f64 * e / (1. - n as f64 * e)
+}
+
+// Largest `Float` at most `x`.
+pub fn round_down(x: f64) -> Float {
+    let y = x as Float;
+    if x.is_nan() || (y as f64) <= x {
+        y
+    } else if y > 0. {
+        Float::from_bits(y.to_bits() - 1)
+    } else if y < 0. {
+        Float::from_bits(y.to_bits() + 1)
+    } else {
+        -Float::from_bits(1)
+    }
+}
+
+// Smallest `Float` at least `x`.
+pub fn round_up(x: f64) -> Float {
+    -round_down(-x)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Vec3f {
+    pub x: Float,
+    pub y: Float,
+    pub z: Float,
+}
+
+impl Vec3f {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn dot(u: Self, v: Self) -> Float {
+        u.x * v.x + u.y * v.y + u.z * v.z
+    }
+
+    pub fn cross(u: Self, v: Self) -> Self {
+        Self::new(
+            u.y * v.z - u.z * v.y,
+            u.z * v.x - u.x * v.z,
+            u.x * v.y - u.y * v.x,
+        )
+    }
+}
+
+impl From<Vec3> for Vec3f {
+    fn from(v: Vec3) -> Self {
+        Self::new(v.x as Float, v.y as Float, v.z as Float)
+    }
+}
+
+impl From<Vec3f> for Vec3 {
+    fn from(v: Vec3f) -> Self {
+        Vec3::new(v.x as f64, v.y as f64, v.z as f64)
+    }
+}
+
+impl Add for Vec3f {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vec3f {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<Float> for Vec3f {
+    type Output = Self;
+    fn mul(self, t: Float) -> Self {
+        Self::new(self.x * t, self.y * t, self.z * t)
+    }
+}
+
+impl Index<usize> for Vec3f {
+    type Output = Float;
+    fn index(&self, i: usize) -> &Float {
+        match i {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("Vec3f: index out of range"),
+        }
+    }
+}
+
+// A ray converted once for testing against many boxes and triangles.
+#[derive(Clone, Copy)]
+pub struct FloatRay {
+    pub orig: Vec3f,
+    pub dir: Vec3f,
+    pub inv_dir: Vec3f,
+    pub dir_is_neg: [bool; 3],
+}
+
+impl FloatRay {
+    pub fn new(r: Ray) -> Self {
+        let dir = Vec3f::from(r.dir);
+        Self {
+            orig: r.orig.into(),
+            dir,
+            inv_dir: Vec3f::new(1. / dir.x, 1. / dir.y, 1. / dir.z),
+            dir_is_neg: [dir.x < 0., dir.y < 0., dir.z < 0.],
+        }
+    }
+}
+
+// An AABB stored in `Float`, rounded outwards so it still contains the original.
+#[derive(Clone, Copy)]
+pub struct FloatBox {
+    pub min: Vec3f,
+    pub max: Vec3f,
+}
+
+impl From<AABB> for FloatBox {
+    fn from(b: AABB) -> Self {
+        Self {
+            min: Vec3f::new(
+                round_down(b.min.x),
+                round_down(b.min.y),
+                round_down(b.min.z),
+            ),
+            max: Vec3f::new(round_up(b.max.x), round_up(b.max.y), round_up(b.max.z)),
+        }
+    }
+}
+
+impl From<FloatBox> for AABB {
+    fn from(b: FloatBox) -> Self {
+        AABB::new(b.min.into(), b.max.into())
+    }
+}
+
+impl FloatBox {
+    // Widened by the rounding error of the slab distances, so that a ray grazing
+    // the box is never missed. The slab planes are picked by the sign of the
+    // direction instead of sorting the distances.
+    pub fn hit(&self, r: &FloatRay, t_min: Float, t_max: Float) -> bool {
+        let widen = (1. + 2. * gamma(3)) as Float;
+        let bounds = [self.min, self.max];
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for (i, &neg) in r.dir_is_neg.iter().enumerate() {
+            let neg = neg as usize;
+            let t0 = (bounds[neg][i] - r.orig[i]) * r.inv_dir[i];
+            let t1 = (bounds[1 - neg][i] - r.orig[i]) * r.inv_dir[i] * widen;
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+        }
+        t_min <= t_max
+    }
+}
@@ -1,5 +1,10 @@
 pub mod camera;
+pub mod distribution;
+pub mod float;
 pub mod onb;
 pub mod pdf;
+pub mod poly;
 pub mod ray;
+pub mod simd;
+pub mod transform;
 pub mod vec;
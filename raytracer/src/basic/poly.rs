@@ -0,0 +1,198 @@
+// Real roots of low degree polynomials. Coefficients are given from the constant
+// term up, so `c[0] + c[1] x + c[2] x^2 + ...`.
+
+const NEWTON_ITERATIONS: usize = 64;
+// Roots closer than this, relative to their size, are one double root.
+const DOUBLE_ROOT_GAP: f64 = 1e-6;
+
+fn eval(c: &[f64], x: f64) -> f64 {
+    c.iter().rev().fold(0., |acc, &a| acc * x + a)
+}
+
+// Both roots of a x^2 + b x + c in ascending order. A linear equation gives its
+// only root twice.
+#[allow(clippy::many_single_char_names)]
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if a == 0. {
+        if b == 0. {
+            return None;
+        }
+        return Some((-c / b, -c / b));
+    }
+    let discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    // Avoids the cancellation of -b + sqrt(discriminant)
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0. {
+        return Some((0., 0.));
+    }
+    let (x0, x1) = (q / a, c / q);
+    Some((x0.min(x1), x0.max(x1)))
+}
+
+// The root in [lo, hi] of a polynomial which is monotonic there, if any.
+fn monotonic_root(c: &[f64], dc: &[f64], mut lo: f64, mut hi: f64) -> Option<f64> {
+    let f_lo = eval(c, lo);
+    let f_hi = eval(c, hi);
+    if f_lo == 0. {
+        return Some(lo);
+    }
+    if f_hi == 0. {
+        return Some(hi);
+    }
+    if f_lo.signum() == f_hi.signum() {
+        return None;
+    }
+
+    // Newton's method, falling back to bisection whenever it leaves the bracket
+    let increasing = f_lo < 0.;
+    let mut x = 0.5 * (lo + hi);
+    for _ in 0..NEWTON_ITERATIONS {
+        let f = eval(c, x);
+        if f == 0. {
+            return Some(x);
+        }
+        if (f < 0.) == increasing {
+            lo = x;
+        } else {
+            hi = x;
+        }
+        let df = eval(dc, x);
+        let mut next = x - f / df;
+        if !(next > lo && next < hi) {
+            next = 0.5 * (lo + hi);
+        }
+        if (next - x).abs() <= f64::EPSILON * x.abs() || hi - lo <= f64::EPSILON * x.abs() {
+            return Some(next);
+        }
+        x = next;
+    }
+    Some(x)
+}
+
+fn derivative(c: &[f64]) -> Vec<f64> {
+    c.iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &a)| i as f64 * a)
+        .collect()
+}
+
+// Splits [lo, hi] at the given critical points and looks for one root in each piece.
+fn roots_between(c: &[f64], critical: &[f64], lo: f64, hi: f64, roots: &mut Vec<f64>) {
+    let dc = derivative(c);
+    let mut start = lo;
+    for &end in critical
+        .iter()
+        .filter(|&&x| x > lo && x < hi)
+        .chain(std::iter::once(&hi))
+    {
+        if let Some(x) = monotonic_root(c, &dc, start, end) {
+            // A double root sits on a critical point, so the pieces on both sides of it
+            // may each find it, a few rounding errors apart
+            match roots.last_mut() {
+                Some(last) if x - *last <= DOUBLE_ROOT_GAP * (1. + x.abs()) => {
+                    *last = 0.5 * (*last + x)
+                }
+                _ => roots.push(x),
+            }
+        }
+        start = end;
+    }
+}
+
+// Roots of c[0] + c[1] x + c[2] x^2 + c[3] x^3 in [lo, hi], ascending.
+pub fn solve_cubic(c: [f64; 4], lo: f64, hi: f64) -> Vec<f64> {
+    let mut critical = Vec::with_capacity(2);
+    if let Some((x0, x1)) = solve_quadratic(3. * c[3], 2. * c[2], c[1]) {
+        critical.push(x0);
+        critical.push(x1);
+    }
+    let mut roots = Vec::with_capacity(3);
+    roots_between(&c, &critical, lo, hi, &mut roots);
+    roots
+}
+
+// Roots of c[0] + c[1] x + ... + c[4] x^4 in [lo, hi], ascending. Unlike the closed
+// form solution, this stays accurate near double roots, e.g. at grazing rays.
+pub fn solve_quartic(c: [f64; 5], lo: f64, hi: f64) -> Vec<f64> {
+    let critical = solve_cubic([c[1], 2. * c[2], 3. * c[3], 4. * c[4]], lo, hi);
+    let mut roots = Vec::with_capacity(4);
+    roots_between(&c, &critical, lo, hi, &mut roots);
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Coefficients of the polynomial with these roots and leading coefficient `a`.
+    fn from_roots(a: f64, roots: &[f64]) -> Vec<f64> {
+        let mut c = vec![a];
+        for &r in roots {
+            let mut next = vec![0.; c.len() + 1];
+            for (i, &x) in c.iter().enumerate() {
+                next[i + 1] += x;
+                next[i] -= r * x;
+            }
+            c = next;
+        }
+        c
+    }
+
+    fn assert_roots(found: &[f64], expected: &[f64]) {
+        assert_eq!(found.len(), expected.len(), "{:?} != {:?}", found, expected);
+        for (x, y) in found.iter().zip(expected) {
+            assert!((x - y).abs() < 1e-6, "{:?} != {:?}", found, expected);
+        }
+    }
+
+    #[test]
+    fn quadratic() {
+        assert_eq!(solve_quadratic(1., -3., 2.), Some((1., 2.)));
+        assert_eq!(solve_quadratic(1., -2., 1.), Some((1., 1.)));
+        assert_eq!(solve_quadratic(0., 2., -1.), Some((0.5, 0.5)));
+        assert_eq!(solve_quadratic(1., 0., 1.), None);
+    }
+
+    #[test]
+    fn cubic() {
+        let c = from_roots(2., &[-1., 0.5, 3.]);
+        assert_roots(
+            &solve_cubic([c[0], c[1], c[2], c[3]], -10., 10.),
+            &[-1., 0.5, 3.],
+        );
+        assert_roots(&solve_cubic([c[0], c[1], c[2], c[3]], 0., 10.), &[0.5, 3.]);
+    }
+
+    #[test]
+    fn quartic_simple_roots() {
+        let c = from_roots(1., &[-2., -0.5, 1., 4.]);
+        let c = [c[0], c[1], c[2], c[3], c[4]];
+        assert_roots(&solve_quartic(c, -10., 10.), &[-2., -0.5, 1., 4.]);
+        assert_roots(&solve_quartic(c, 0., 2.), &[1.]);
+        assert_roots(&solve_quartic(c, 5., 10.), &[]);
+    }
+
+    #[test]
+    fn quartic_double_roots() {
+        let c = from_roots(1., &[1., 1., 2., 3.]);
+        assert_roots(
+            &solve_quartic([c[0], c[1], c[2], c[3], c[4]], -10., 10.),
+            &[1., 2., 3.],
+        );
+        let c = from_roots(-3., &[-1., -1., 2., 2.]);
+        assert_roots(
+            &solve_quartic([c[0], c[1], c[2], c[3], c[4]], -10., 10.),
+            &[-1., 2.],
+        );
+    }
+
+    #[test]
+    fn quartic_without_real_roots() {
+        // (x^2 + 1)(x^2 + 4)
+        assert_roots(&solve_quartic([4., 0., 5., 0., 1.], -10., 10.), &[]);
+    }
+}
@@ -0,0 +1,304 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::float::Float;
+
+// Four `Float` lanes, in SSE registers on x86_64 (two of them for f64) and in a
+// plain array elsewhere. `min` and `max` return their second argument when either
+// is NaN on every target, like the SSE instructions do.
+#[derive(Clone, Copy)]
+pub struct F4(imp::Lanes);
+
+impl F4 {
+    #[inline]
+    pub fn load(a: &[Float; 4]) -> Self {
+        F4(imp::load(a))
+    }
+
+    #[inline]
+    pub fn splat(x: Float) -> Self {
+        F4(imp::splat(x))
+    }
+
+    #[inline]
+    pub fn to_array(self) -> [Float; 4] {
+        imp::to_array(self.0)
+    }
+
+    #[inline]
+    pub fn min(a: Self, b: Self) -> Self {
+        F4(imp::min(a.0, b.0))
+    }
+
+    #[inline]
+    pub fn max(a: Self, b: Self) -> Self {
+        F4(imp::max(a.0, b.0))
+    }
+
+    // Bit i is set when lane i of `a` is below that of `b`, false for NaN.
+    #[inline]
+    pub fn lt(a: Self, b: Self) -> u32 {
+        imp::lt(a.0, b.0)
+    }
+
+    #[inline]
+    pub fn le(a: Self, b: Self) -> u32 {
+        imp::le(a.0, b.0)
+    }
+}
+
+impl Add for F4 {
+    type Output = Self;
+    #[inline]
+    fn add(self, other: Self) -> Self {
+        F4(imp::add(self.0, other.0))
+    }
+}
+
+impl Sub for F4 {
+    type Output = Self;
+    #[inline]
+    fn sub(self, other: Self) -> Self {
+        F4(imp::sub(self.0, other.0))
+    }
+}
+
+impl Mul for F4 {
+    type Output = Self;
+    #[inline]
+    fn mul(self, other: Self) -> Self {
+        F4(imp::mul(self.0, other.0))
+    }
+}
+
+impl Div for F4 {
+    type Output = Self;
+    #[inline]
+    fn div(self, other: Self) -> Self {
+        F4(imp::div(self.0, other.0))
+    }
+}
+
+pub fn dot(u: &[F4; 3], v: &[F4; 3]) -> F4 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
+pub fn cross(u: &[F4; 3], v: &[F4; 3]) -> [F4; 3] {
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+// SSE is part of x86_64, so the intrinsics need no runtime check.
+#[cfg(all(target_arch = "x86_64", feature = "f32"))]
+mod imp {
+    use std::arch::x86_64::*;
+
+    pub type Lanes = __m128;
+
+    #[inline]
+    pub fn load(a: &[f32; 4]) -> Lanes {
+        unsafe { _mm_loadu_ps(a.as_ptr()) }
+    }
+
+    #[inline]
+    pub fn splat(x: f32) -> Lanes {
+        unsafe { _mm_set1_ps(x) }
+    }
+
+    #[inline]
+    pub fn to_array(a: Lanes) -> [f32; 4] {
+        let mut out = [0.; 4];
+        unsafe { _mm_storeu_ps(out.as_mut_ptr(), a) };
+        out
+    }
+
+    #[inline]
+    pub fn add(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { _mm_add_ps(a, b) }
+    }
+
+    #[inline]
+    pub fn sub(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { _mm_sub_ps(a, b) }
+    }
+
+    #[inline]
+    pub fn mul(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { _mm_mul_ps(a, b) }
+    }
+
+    #[inline]
+    pub fn div(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { _mm_div_ps(a, b) }
+    }
+
+    #[inline]
+    pub fn min(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { _mm_min_ps(a, b) }
+    }
+
+    #[inline]
+    pub fn max(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { _mm_max_ps(a, b) }
+    }
+
+    #[inline]
+    pub fn lt(a: Lanes, b: Lanes) -> u32 {
+        unsafe { _mm_movemask_ps(_mm_cmplt_ps(a, b)) as u32 }
+    }
+
+    #[inline]
+    pub fn le(a: Lanes, b: Lanes) -> u32 {
+        unsafe { _mm_movemask_ps(_mm_cmple_ps(a, b)) as u32 }
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", not(feature = "f32")))]
+mod imp {
+    use std::arch::x86_64::*;
+
+    // Lanes 0 and 1, then 2 and 3
+    pub type Lanes = (__m128d, __m128d);
+
+    #[inline]
+    pub fn load(a: &[f64; 4]) -> Lanes {
+        unsafe { (_mm_loadu_pd(a.as_ptr()), _mm_loadu_pd(a[2..].as_ptr())) }
+    }
+
+    #[inline]
+    pub fn splat(x: f64) -> Lanes {
+        unsafe { (_mm_set1_pd(x), _mm_set1_pd(x)) }
+    }
+
+    #[inline]
+    pub fn to_array(a: Lanes) -> [f64; 4] {
+        let mut out = [0.; 4];
+        unsafe {
+            _mm_storeu_pd(out.as_mut_ptr(), a.0);
+            _mm_storeu_pd(out[2..].as_mut_ptr(), a.1);
+        }
+        out
+    }
+
+    #[inline]
+    pub fn add(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { (_mm_add_pd(a.0, b.0), _mm_add_pd(a.1, b.1)) }
+    }
+
+    #[inline]
+    pub fn sub(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { (_mm_sub_pd(a.0, b.0), _mm_sub_pd(a.1, b.1)) }
+    }
+
+    #[inline]
+    pub fn mul(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { (_mm_mul_pd(a.0, b.0), _mm_mul_pd(a.1, b.1)) }
+    }
+
+    #[inline]
+    pub fn div(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { (_mm_div_pd(a.0, b.0), _mm_div_pd(a.1, b.1)) }
+    }
+
+    #[inline]
+    pub fn min(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { (_mm_min_pd(a.0, b.0), _mm_min_pd(a.1, b.1)) }
+    }
+
+    #[inline]
+    pub fn max(a: Lanes, b: Lanes) -> Lanes {
+        unsafe { (_mm_max_pd(a.0, b.0), _mm_max_pd(a.1, b.1)) }
+    }
+
+    #[inline]
+    pub fn lt(a: Lanes, b: Lanes) -> u32 {
+        unsafe {
+            let low = _mm_movemask_pd(_mm_cmplt_pd(a.0, b.0));
+            let high = _mm_movemask_pd(_mm_cmplt_pd(a.1, b.1));
+            (low | high << 2) as u32
+        }
+    }
+
+    #[inline]
+    pub fn le(a: Lanes, b: Lanes) -> u32 {
+        unsafe {
+            let low = _mm_movemask_pd(_mm_cmple_pd(a.0, b.0));
+            let high = _mm_movemask_pd(_mm_cmple_pd(a.1, b.1));
+            (low | high << 2) as u32
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod imp {
+    use super::Float;
+
+    pub type Lanes = [Float; 4];
+
+    #[inline]
+    fn map(a: Lanes, b: Lanes, f: impl Fn(Float, Float) -> Float) -> Lanes {
+        [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2]), f(a[3], b[3])]
+    }
+
+    #[inline]
+    fn mask(a: Lanes, b: Lanes, f: impl Fn(Float, Float) -> bool) -> u32 {
+        (0..4).fold(0, |bits, i| bits | (f(a[i], b[i]) as u32) << i)
+    }
+
+    #[inline]
+    pub fn load(a: &[Float; 4]) -> Lanes {
+        *a
+    }
+
+    #[inline]
+    pub fn splat(x: Float) -> Lanes {
+        [x; 4]
+    }
+
+    #[inline]
+    pub fn to_array(a: Lanes) -> [Float; 4] {
+        a
+    }
+
+    #[inline]
+    pub fn add(a: Lanes, b: Lanes) -> Lanes {
+        map(a, b, |x, y| x + y)
+    }
+
+    #[inline]
+    pub fn sub(a: Lanes, b: Lanes) -> Lanes {
+        map(a, b, |x, y| x - y)
+    }
+
+    #[inline]
+    pub fn mul(a: Lanes, b: Lanes) -> Lanes {
+        map(a, b, |x, y| x * y)
+    }
+
+    #[inline]
+    pub fn div(a: Lanes, b: Lanes) -> Lanes {
+        map(a, b, |x, y| x / y)
+    }
+
+    #[inline]
+    pub fn min(a: Lanes, b: Lanes) -> Lanes {
+        map(a, b, |x, y| if x < y { x } else { y })
+    }
+
+    #[inline]
+    pub fn max(a: Lanes, b: Lanes) -> Lanes {
+        map(a, b, |x, y| if x > y { x } else { y })
+    }
+
+    #[inline]
+    pub fn lt(a: Lanes, b: Lanes) -> u32 {
+        mask(a, b, |x, y| x < y)
+    }
+
+    #[inline]
+    pub fn le(a: Lanes, b: Lanes) -> u32 {
+        mask(a, b, |x, y| x <= y)
+    }
+}
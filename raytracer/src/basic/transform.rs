@@ -0,0 +1,363 @@
+use std::ops::Mul;
+
+use super::vec::{Point3, Vec3};
+
+pub type Matrix4 = [[f64; 4]; 4];
+
+const IDENTITY: Matrix4 = [
+    [1., 0., 0., 0.],
+    [0., 1., 0., 0.],
+    [0., 0., 1., 0.],
+    [0., 0., 0., 1.],
+];
+
+// An affine transform, kept together with its inverse. Matrices are row-major
+// and act on column vectors, so `a * b` applies `b` first.
+#[derive(Clone, Copy)]
+pub struct Transform {
+    m: Matrix4,
+    m_inv: Matrix4,
+}
+
+#[allow(dead_code)]
+impl Transform {
+    pub fn identity() -> Self {
+        Self {
+            m: IDENTITY,
+            m_inv: IDENTITY,
+        }
+    }
+
+    // None if `m` cannot be inverted.
+    pub fn new(m: Matrix4) -> Option<Self> {
+        Some(Self {
+            m,
+            m_inv: inverse(&m)?,
+        })
+    }
+
+    pub fn translate(offset: Vec3) -> Self {
+        let mut m = IDENTITY;
+        let mut m_inv = IDENTITY;
+        for i in 0..3 {
+            m[i][3] = offset[i];
+            m_inv[i][3] = -offset[i];
+        }
+        Self { m, m_inv }
+    }
+
+    // Zero factors make the transform singular, so they are not allowed.
+    pub fn scale(factor: Vec3) -> Self {
+        assert!(factor.x != 0. && factor.y != 0. && factor.z != 0.);
+        let mut m = IDENTITY;
+        let mut m_inv = IDENTITY;
+        for i in 0..3 {
+            m[i][i] = factor[i];
+            m_inv[i][i] = 1. / factor[i];
+        }
+        Self { m, m_inv }
+    }
+
+    // Scales, then rotates, then translates.
+    pub fn from_trs(translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        assert!(scale.x != 0. && scale.y != 0. && scale.z != 0.);
+        let r = rotation.matrix();
+        let mut m = IDENTITY;
+        let mut m_inv = IDENTITY;
+        for i in 0..3 {
+            for j in 0..3 {
+                m[i][j] = r[i][j] * scale[j];
+                m_inv[i][j] = r[j][i] / scale[i];
+            }
+            m[i][3] = translation[i];
+        }
+        for row in m_inv.iter_mut().take(3) {
+            row[3] = -(0..3).map(|j| row[j] * translation[j]).sum::<f64>();
+        }
+        Self { m, m_inv }
+    }
+
+    // Counterclockwise around `axis`, seen from its tip.
+    pub fn rotate(axis: Vec3, degrees: f64) -> Self {
+        let a = axis.to_unit();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut m = IDENTITY;
+        for i in 0..3 {
+            for j in 0..3 {
+                m[i][j] = a[i] * a[j] * (1. - cos) + if i == j { cos } else { 0. };
+            }
+        }
+        m[0][1] -= a.z * sin;
+        m[0][2] += a.y * sin;
+        m[1][0] += a.z * sin;
+        m[1][2] -= a.x * sin;
+        m[2][0] -= a.y * sin;
+        m[2][1] += a.x * sin;
+        Self {
+            m,
+            m_inv: transpose(&m),
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self {
+            m: self.m_inv,
+            m_inv: self.m,
+        }
+    }
+
+    pub fn matrix(&self) -> Matrix4 {
+        self.m
+    }
+
+    pub fn inverse_matrix(&self) -> Matrix4 {
+        self.m_inv
+    }
+
+    pub fn inverse_transpose(&self) -> Matrix4 {
+        transpose(&self.m_inv)
+    }
+
+    pub fn point(&self, p: Point3) -> Point3 {
+        self.vector(p) + Vec3::new(self.m[0][3], self.m[1][3], self.m[2][3])
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    // Bound on the error of a transformed point whose error was `e` in each axis.
+    pub fn error(&self, e: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0].abs() * e.x + m[0][1].abs() * e.y + m[0][2].abs() * e.z,
+            m[1][0].abs() * e.x + m[1][1].abs() * e.y + m[1][2].abs() * e.z,
+            m[2][0].abs() * e.x + m[2][1].abs() * e.y + m[2][2].abs() * e.z,
+        )
+    }
+
+    // Normals go through the inverse transpose, the result is not normalized.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        let m = &self.m_inv;
+        Vec3::new(
+            m[0][0] * n.x + m[1][0] * n.y + m[2][0] * n.z,
+            m[0][1] * n.x + m[1][1] * n.y + m[2][1] * n.z,
+            m[0][2] * n.x + m[1][2] * n.y + m[2][2] * n.z,
+        )
+    }
+
+    // Determinant of the linear part, negative for mirroring transforms.
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
+
+impl Mul for Transform {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self {
+            m: multiply(&self.m, &other.m),
+            m_inv: multiply(&other.m_inv, &self.m_inv),
+        }
+    }
+}
+
+// Unit quaternion for rotations, `w` is the real part.
+#[derive(Clone, Copy)]
+pub struct Quaternion {
+    pub w: f64,
+    pub v: Vec3,
+}
+
+#[allow(dead_code)]
+impl Quaternion {
+    pub fn identity() -> Self {
+        Self {
+            w: 1.,
+            v: Vec3::new(0., 0., 0.),
+        }
+    }
+
+    pub fn from_axis_angle(axis: Vec3, degrees: f64) -> Self {
+        let (sin, cos) = (degrees.to_radians() / 2.).sin_cos();
+        Self {
+            w: cos,
+            v: axis.to_unit() * sin,
+        }
+    }
+
+    fn dot(a: Self, b: Self) -> f64 {
+        a.w * b.w + Vec3::dot(a.v, b.v)
+    }
+
+    fn normalize(self) -> Self {
+        let len = Self::dot(self, self).sqrt();
+        Self {
+            w: self.w / len,
+            v: self.v / len,
+        }
+    }
+
+    // Constant angular speed along the shorter arc from `a` to `b`.
+    pub fn slerp(a: Self, b: Self, t: f64) -> Self {
+        let mut cos = Self::dot(a, b);
+        let mut b = b;
+        if cos < 0. {
+            cos = -cos;
+            b = Self { w: -b.w, v: -b.v };
+        }
+        let (wa, wb) = if cos > 0.9995 {
+            (1. - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1. - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Self {
+            w: a.w * wa + b.w * wb,
+            v: a.v * wa + b.v * wb,
+        }
+        .normalize()
+    }
+
+    // Angle between the two rotations, in radians.
+    pub fn angle(a: Self, b: Self) -> f64 {
+        2. * Self::dot(a, b).abs().min(1.).acos()
+    }
+
+    pub fn matrix(&self) -> [[f64; 3]; 3] {
+        let (w, x, y, z) = (self.w, self.v.x, self.v.y, self.v.z);
+        [
+            [
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - w * z),
+                2. * (x * z + w * y),
+            ],
+            [
+                2. * (x * y + w * z),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - w * x),
+            ],
+            [
+                2. * (x * z - w * y),
+                2. * (y * z + w * x),
+                1. - 2. * (x * x + y * y),
+            ],
+        ]
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn new(time: f64, translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        Self {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+}
+
+// A transform interpolated between keyframes, held constant before the first and after the last.
+#[derive(Clone)]
+pub struct AnimatedTransform {
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty());
+        keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        Self { keyframes }
+    }
+
+    pub fn keyframe_at(&self, time: f64) -> Keyframe {
+        let keys = &self.keyframes;
+        let next = keys.iter().position(|k| k.time > time);
+        let (a, b) = match next {
+            None => return keys[keys.len() - 1],
+            Some(0) => return keys[0],
+            Some(i) => (keys[i - 1], keys[i]),
+        };
+        let t = (time - a.time) / (b.time - a.time);
+        Keyframe::new(
+            time,
+            a.translation * (1. - t) + b.translation * t,
+            Quaternion::slerp(a.rotation, b.rotation, t),
+            a.scale * (1. - t) + b.scale * t,
+        )
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        let k = self.keyframe_at(time);
+        Transform::from_trs(k.translation, k.rotation, k.scale)
+    }
+}
+
+fn multiply(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut m = [[0.; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[i][k] * b[k][j]).sum();
+        }
+    }
+    m
+}
+
+fn transpose(a: &Matrix4) -> Matrix4 {
+    let mut m = [[0.; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = a[j][i];
+        }
+    }
+    m
+}
+
+// Gauss-Jordan elimination with partial pivoting.
+#[allow(clippy::needless_range_loop)]
+fn inverse(a: &Matrix4) -> Option<Matrix4> {
+    let mut a = *a;
+    let mut inv = IDENTITY;
+    for col in 0..4 {
+        let pivot = (col..4)
+            .max_by(|&i, &j| a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap())
+            .unwrap();
+        if a[pivot][col].abs() < 1e-12 || !a[pivot][col].is_finite() {
+            return None;
+        }
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+        let scale = 1. / a[col][col];
+        for j in 0..4 {
+            a[col][j] *= scale;
+            inv[col][j] *= scale;
+        }
+        for i in 0..4 {
+            if i != col {
+                let factor = a[i][col];
+                for j in 0..4 {
+                    a[i][j] -= factor * a[col][j];
+                    inv[i][j] -= factor * inv[col][j];
+                }
+            }
+        }
+    }
+    Some(inv)
+}
@@ -59,6 +59,10 @@ impl Vec3 {
         random_in_unit_sphere().to_unit()
     }
 
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
+
     #[allow(dead_code)]
     pub fn near_zero(&self) -> bool {
         let eps = 1e-8;
@@ -0,0 +1,264 @@
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use console::style;
+use rand::Rng;
+
+use crate::{
+    basic::{
+        float::FloatRay,
+        ray::Ray,
+        vec::{random_in_unit_xz_disk, Color, Point3, Vec3},
+    },
+    hittable::{
+        bvh::{aabb::AABB, linear::LinearBvh, split::SplitMethod, BvhNode},
+        mesh::{MeshData, TriangleMesh},
+        ring::Ring,
+        sphere::Sphere,
+        HitRecord, Hittable, HittableList,
+    },
+    material::Lambertian,
+};
+
+const RAYS: usize = 1_000_000;
+
+// Counts the rays tested against the object it wraps.
+struct Counted {
+    object: Arc<dyn Hittable>,
+    tests: Arc<AtomicUsize>,
+}
+
+impl Hittable for Counted {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.tests.fetch_add(1, Ordering::Relaxed);
+        self.object.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        self.object.bounding_box(time0, time1)
+    }
+}
+
+// Objects of very different sizes, laid out like `wwscene`: a far away sun, three
+// planets, one of them with rings and small stars among them, and more stars
+// around.
+fn uneven_scene(tests: &Arc<AtomicUsize>) -> HittableList {
+    let mut rng = rand::thread_rng();
+    let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
+    let gray = Color::new(0.5, 0.5, 0.5);
+
+    for (center, radius) in [
+        (Point3::new(800., 700., -800.), 70.),
+        (Point3::new(0., 0., 0.), 43.),
+        (Point3::new(150., 20., 150.), 26.),
+        (Point3::new(480., 25., 500.), 25.),
+    ]
+    .iter()
+    {
+        objects.push(Arc::new(Sphere::new(
+            *center,
+            *radius,
+            Lambertian::new(gray),
+        )));
+    }
+    for _ in 0..2000 {
+        let center = random_in_unit_xz_disk().to_unit() * (100. + rng.gen_range(-15.0..15.))
+            + Vec3::new(0., 0., rng.gen_range(-1.0..1.));
+        objects.push(Arc::new(Sphere::new(
+            center,
+            rng.gen_range(0.3..0.6),
+            Lambertian::new(gray),
+        )));
+    }
+    for i in 0..130 {
+        let radius = 80. + i as f64 * 0.3;
+        objects.push(Arc::new(Ring::new(radius, 0.01, Lambertian::new(gray))));
+    }
+    for _ in 0..2000 {
+        let center = Point3::new(
+            rng.gen_range(-500.0..500.),
+            rng.gen_range(-500.0..500.),
+            rng.gen_range(100.0..400.),
+        );
+        objects.push(Arc::new(Sphere::new(
+            center,
+            rng.gen_range(0.3..0.45),
+            Lambertian::new(gray),
+        )));
+    }
+
+    HittableList {
+        objects: objects
+            .into_iter()
+            .map(|object| {
+                Arc::new(Counted {
+                    object,
+                    tests: tests.clone(),
+                }) as Arc<dyn Hittable>
+            })
+            .collect(),
+    }
+}
+
+// Builds the BVH of an uneven scene with each split method, then traces the same
+// rays through both and reports the objects tested per ray and the speed.
+pub fn bvh() {
+    let tests = Arc::new(AtomicUsize::new(0));
+    let world = uneven_scene(&tests);
+
+    let rays: Vec<Ray> = (0..RAYS)
+        .map(|_| {
+            let orig = Vec3::random_unit_vector() * 300.;
+            let target = Vec3::random(-120., 120.) * Vec3::new(1., 0.1, 1.);
+            Ray::new(orig, target - orig, 0.)
+        })
+        .collect();
+
+    println!(
+        "{} objects, {} rays",
+        style(world.objects.len().to_string()).yellow(),
+        style(RAYS.to_string()).yellow()
+    );
+    for (name, method) in [
+        ("equal counts", SplitMethod::EqualCounts),
+        ("SAH", SplitMethod::Sah),
+    ]
+    .iter()
+    {
+        let begin = Instant::now();
+        let bvh = BvhNode::with_method(world.objects.clone(), 0., 1., *method).unwrap();
+        let build_time = begin.elapsed();
+
+        tests.store(0, Ordering::Relaxed);
+        let begin = Instant::now();
+        let mut hits = 0;
+        for r in rays.iter() {
+            if bvh.hit(*r, 0.001, f64::INFINITY).is_some() {
+                hits += 1;
+            }
+        }
+        let trace_time = begin.elapsed();
+        println!(
+            "{:>12}: built in {:.1} ms, {:.2} objects tested per ray, {:.2} Mrays/s, {} hits",
+            name,
+            build_time.as_secs_f64() * 1e3,
+            tests.load(Ordering::Relaxed) as f64 / RAYS as f64,
+            RAYS as f64 / trace_time.as_secs_f64() / 1e6,
+            hits
+        );
+    }
+}
+
+// All the faces of the shuttle in one mesh, or a bumpy sphere of about as many if it
+// cannot be loaded. Only the positions are read, so a missing material library
+// does not matter here.
+fn shuttle_mesh() -> MeshData {
+    let options = tobj::LoadOptions {
+        single_index: true,
+        triangulate: true,
+        ..Default::default()
+    };
+    match tobj::load_obj("source/obj/Shuttle.obj", &options) {
+        Ok((models, _)) => {
+            let mut positions = Vec::new();
+            let mut indices = Vec::new();
+            for m in models {
+                let offset = positions.len() as u32;
+                positions.extend(
+                    m.mesh
+                        .positions
+                        .chunks_exact(3)
+                        .map(|p| Point3::new(p[0] as f64, p[1] as f64, p[2] as f64)),
+                );
+                indices.extend(
+                    m.mesh
+                        .indices
+                        .chunks_exact(3)
+                        .map(|f| [f[0] + offset, f[1] + offset, f[2] + offset]),
+                );
+            }
+            MeshData::new(positions, indices)
+        }
+        Err(e) => {
+            println!("{}", style(format!("Loading shuttle fails: {}", e)).red());
+            let (nu, nv) = (300, 150);
+            let mut positions = Vec::with_capacity((nu + 1) * (nv + 1));
+            for j in 0..=nv {
+                for i in 0..=nu {
+                    let phi = i as f64 / nu as f64 * 2. * PI;
+                    let theta = j as f64 / nv as f64 * PI;
+                    let radius = 1. + 0.05 * (7. * phi).sin() * (5. * theta).sin();
+                    positions.push(
+                        Point3::new(
+                            theta.sin() * phi.cos(),
+                            theta.cos(),
+                            theta.sin() * phi.sin(),
+                        ) * radius,
+                    );
+                }
+            }
+            let mut indices = Vec::with_capacity(2 * nu * nv);
+            for j in 0..nv {
+                for i in 0..nu {
+                    let a = (j * (nu + 1) + i) as u32;
+                    let c = a + nu as u32 + 1;
+                    indices.push([a, c, a + 1]);
+                    indices.push([a + 1, c, c + 1]);
+                }
+            }
+            MeshData::new(positions, indices)
+        }
+    }
+}
+
+// Traces the same rays through a mesh with the binary BVH and one triangle at a
+// time, and with the 4-wide BVH and four triangles at a time.
+pub fn mesh() {
+    let data = Arc::new(shuttle_mesh());
+    let boxes: Vec<AABB> = (0..data.indices.len())
+        .map(|face| data.bounding_box(face))
+        .collect();
+    let scalar = LinearBvh::new(&boxes);
+    let wide = TriangleMesh::new(data.clone(), Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+
+    let aabbox = wide.bounding_box(0., 1.).unwrap();
+    let center = aabbox.centroid();
+    let size = aabbox.max - aabbox.min;
+    let rays: Vec<Ray> = (0..RAYS)
+        .map(|_| {
+            let orig = center + Vec3::random_unit_vector() * size.length();
+            let target = center + Vec3::random(-0.5, 0.5) * size;
+            Ray::new(orig, target - orig, 0.)
+        })
+        .collect();
+
+    println!(
+        "{} triangles, {} rays",
+        style(data.indices.len().to_string()).yellow(),
+        style(RAYS.to_string()).yellow()
+    );
+    let time = |name: &str, hit: &dyn Fn(Ray) -> bool| {
+        let begin = Instant::now();
+        let hits = rays.iter().filter(|r| hit(**r)).count();
+        let speed = RAYS as f64 / begin.elapsed().as_secs_f64() / 1e6;
+        println!("{:>12}: {:.2} Mrays/s, {} hits", name, speed, hits);
+        speed
+    };
+    let mp = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+    let scalar_speed = time("scalar", &|r| {
+        let float_r = FloatRay::new(r);
+        let mut closest = None;
+        scalar.hit(&float_r, 0.001, f64::INFINITY, |face, t_max| {
+            let (t, b1, b2) = data.intersect(face, &float_r, 0.001, t_max)?;
+            closest = Some((face, t, b1, b2));
+            Some(t)
+        });
+        closest
+            .map(|(face, t, b1, b2)| data.hit_record(face, r, t, b1, b2, &mp))
+            .is_some()
+    });
+    let wide_speed = time("4-wide", &|r| wide.hit(r, 0.001, f64::INFINITY).is_some());
+    println!("{:>12}: {:.2}x", "speedup", wide_speed / scalar_speed);
+}
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use crate::{
     basic::ray::Ray,
-    basic::vec::Point3,
+    basic::vec::{Point3, Vec3},
     hittable::aarect::{XYRect, XZRect, YZRect},
     hittable::bvh::aabb::AABB,
     hittable::{Hittable, HittableList},
@@ -20,6 +20,7 @@ impl Boxes {
     where
         M: Material + Clone + 'static,
     {
+        // The sides at p0 are flipped to face outwards too
         let mut sides: HittableList = Default::default();
         sides.add(Arc::new(XYRect::new(
             p0.x,
@@ -29,14 +30,9 @@ impl Boxes {
             p1.z,
             ptr.clone(),
         )));
-        sides.add(Arc::new(XYRect::new(
-            p0.x,
-            p1.x,
-            p0.y,
-            p1.y,
-            p0.z,
-            ptr.clone(),
-        )));
+        sides.add(Arc::new(
+            XYRect::new(p0.x, p1.x, p0.y, p1.y, p0.z, ptr.clone()).flipped(),
+        ));
 
         sides.add(Arc::new(XZRect::new(
             p0.x,
@@ -46,14 +42,9 @@ impl Boxes {
             p1.y,
             ptr.clone(),
         )));
-        sides.add(Arc::new(XZRect::new(
-            p0.x,
-            p1.x,
-            p0.z,
-            p1.z,
-            p0.y,
-            ptr.clone(),
-        )));
+        sides.add(Arc::new(
+            XZRect::new(p0.x, p1.x, p0.z, p1.z, p0.y, ptr.clone()).flipped(),
+        ));
 
         sides.add(Arc::new(YZRect::new(
             p0.y,
@@ -63,7 +54,9 @@ impl Boxes {
             p1.x,
             ptr.clone(),
         )));
-        sides.add(Arc::new(YZRect::new(p0.y, p1.y, p0.z, p1.z, p0.x, ptr)));
+        sides.add(Arc::new(
+            YZRect::new(p0.y, p1.y, p0.z, p1.z, p0.x, ptr).flipped(),
+        ));
 
         Self {
             min: p0,
@@ -80,4 +73,13 @@ impl Hittable for Boxes {
     fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<crate::hittable::HitRecord> {
         self.sides.hit(r, t_min, t_max)
     }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.sides.pdf_value(o, v)
+    }
+    fn random(&self, o: Point3) -> Vec3 {
+        self.sides.random(o)
+    }
+    fn power(&self) -> f64 {
+        self.sides.power()
+    }
 }
@@ -1,5 +1,5 @@
 use crate::basic::ray::Ray;
-use crate::basic::vec::Point3;
+use crate::basic::vec::{Point3, Vec3};
 
 #[derive(Clone, Copy)]
 pub struct AABB {
@@ -12,23 +12,39 @@ impl AABB {
         Self { min, max }
     }
 
+    #[allow(dead_code)]
     pub fn hit(&self, r: Ray, tmin: f64, tmax: f64) -> bool {
+        self.clip(r, tmin, tmax).is_some()
+    }
+
+    // Range of t inside the box, if any.
+    pub fn clip(&self, r: Ray, tmin: f64, tmax: f64) -> Option<(f64, f64)> {
+        let inv_dir = Vec3::new(1. / r.dir.x, 1. / r.dir.y, 1. / r.dir.z);
+        let bounds = [self.min, self.max];
         let mut t_min = tmin;
         let mut t_max = tmax;
         for i in 0..3 {
-            let inv_d = 1. / r.dir[i];
-            let mut t0 = (self.min[i] - r.orig[i]) * inv_d;
-            let mut t1 = (self.max[i] - r.orig[i]) * inv_d;
-            if inv_d < 0. {
-                std::mem::swap(&mut t0, &mut t1);
-            }
-            t_min = if t0 > t_min { t0 } else { t_min };
-            t_max = if t1 < t_max { t1 } else { t_max };
-            if t_max <= t_min {
-                return false;
-            }
+            let neg = (inv_dir[i] < 0.) as usize;
+            let t0 = (bounds[neg][i] - r.orig[i]) * inv_dir[i];
+            let t1 = (bounds[1 - neg][i] - r.orig[i]) * inv_dir[i];
+            // f64::max and f64::min skip the NaN of a ray lying in the slab plane
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
         }
-        true
+        if t_max <= t_min {
+            None
+        } else {
+            Some((t_min, t_max))
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        2. * (d.x * d.y + d.y * d.z + d.z * d.x)
     }
 
     pub fn surrounding_box(box0: Self, box1: Self) -> Self {
@@ -44,4 +60,19 @@ impl AABB {
         );
         AABB::new(small, large)
     }
+
+    // Empty boxes come out with min above max on some axis.
+    pub fn overlap_box(box0: Self, box1: Self) -> Self {
+        let small = Point3::new(
+            f64::max(box0.min.x, box1.min.x),
+            f64::max(box0.min.y, box1.min.y),
+            f64::max(box0.min.z, box1.min.z),
+        );
+        let large = Point3::new(
+            f64::min(box0.max.x, box1.max.x),
+            f64::min(box0.max.y, box1.max.y),
+            f64::min(box0.max.z, box1.max.z),
+        );
+        AABB::new(small, large)
+    }
 }
@@ -0,0 +1,157 @@
+use crate::basic::float::{round_down, round_up, FloatBox, FloatRay};
+
+use super::aabb::AABB;
+use super::split::{split, SplitMethod};
+
+const MAX_LEAF_SIZE: usize = 4;
+const STACK_SIZE: usize = 64;
+
+// Leaves keep `count` primitives starting at `offset` in `order`,
+// interior nodes keep their second child at `offset`, the first one follows them.
+#[derive(Clone, Copy)]
+pub struct LinearNode {
+    pub aabbox: FloatBox,
+    pub offset: u32,
+    pub count: u16,
+    pub axis: u8,
+}
+
+// A BVH over primitives given by index, stored in a flat array.
+#[derive(Clone)]
+pub struct LinearBvh {
+    pub nodes: Vec<LinearNode>,
+    pub order: Vec<u32>,
+}
+
+struct BuildItem {
+    index: u32,
+    aabbox: AABB,
+}
+
+impl LinearBvh {
+    pub fn new(boxes: &[AABB]) -> Self {
+        LinearBvh::with_method(boxes, SplitMethod::Sah)
+    }
+
+    pub fn with_method(boxes: &[AABB], method: SplitMethod) -> Self {
+        let mut items: Vec<BuildItem> = boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| BuildItem {
+                index: i as u32,
+                aabbox: *b,
+            })
+            .collect();
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+            order: Vec::with_capacity(boxes.len()),
+        };
+        if !items.is_empty() {
+            bvh.build(&mut items, method, 0);
+        }
+        bvh
+    }
+
+    fn build(&mut self, items: &mut [BuildItem], method: SplitMethod, depth: usize) -> usize {
+        let node_index = self.nodes.len();
+        let mut aabbox = items[0].aabbox;
+        for item in items.iter() {
+            aabbox = AABB::surrounding_box(aabbox, item.aabbox);
+        }
+        self.nodes.push(LinearNode {
+            aabbox: aabbox.into(),
+            offset: self.order.len() as u32,
+            count: items.len() as u16,
+            axis: 0,
+        });
+
+        let split = if depth + 2 >= STACK_SIZE {
+            None
+        } else {
+            split(items, |item| item.aabbox, method, MAX_LEAF_SIZE)
+        };
+        match split {
+            Some((axis, mid)) => self.split(node_index, items, mid, axis, method, depth),
+            // Past the depth limit a leaf can still be too big to count, split it anyway.
+            None if items.len() > u16::MAX as usize => {
+                self.split(node_index, items, items.len() / 2, 0, method, depth)
+            }
+            None => {
+                self.order.extend(items.iter().map(|item| item.index));
+                node_index
+            }
+        }
+    }
+
+    fn split(
+        &mut self,
+        node_index: usize,
+        items: &mut [BuildItem],
+        mid: usize,
+        axis: usize,
+        method: SplitMethod,
+        depth: usize,
+    ) -> usize {
+        let (left, right) = items.split_at_mut(mid);
+        self.build(left, method, depth + 1);
+        let second = self.build(right, method, depth + 1);
+        let node = &mut self.nodes[node_index];
+        node.offset = second as u32;
+        node.count = 0;
+        node.axis = axis as u8;
+        node_index
+    }
+
+    // Box around all the primitives.
+    pub fn bounds(&self) -> Option<AABB> {
+        self.nodes.first().map(|node| node.aabbox.into())
+    }
+
+    // Visits the primitives whose boxes are hit, front to back. `hit_primitive` gets the
+    // primitive index and the current `t_max`, and returns the distance of a closer hit.
+    pub fn hit<F>(&self, r: &FloatRay, t_min: f64, t_max: f64, mut hit_primitive: F) -> bool
+    where
+        F: FnMut(usize, f64) -> Option<f64>,
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let t_min_f = round_down(t_min);
+        let mut closest_so_far = t_max;
+        let mut hit_anything = false;
+        let mut stack = [0usize; STACK_SIZE];
+        let mut stack_size = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.aabbox.hit(r, t_min_f, round_up(closest_so_far)) {
+                if node.count > 0 {
+                    let begin = node.offset as usize;
+                    for &index in &self.order[begin..begin + node.count as usize] {
+                        if let Some(t) = hit_primitive(index as usize, closest_so_far) {
+                            closest_so_far = t;
+                            hit_anything = true;
+                        }
+                    }
+                } else {
+                    // Visit the child nearer to the ray origin first
+                    let (near, far) = if r.dir_is_neg[node.axis as usize] {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+                    stack[stack_size] = far;
+                    stack_size += 1;
+                    current = near;
+                    continue;
+                }
+            }
+            if stack_size == 0 {
+                break;
+            }
+            stack_size -= 1;
+            current = stack[stack_size];
+        }
+        hit_anything
+    }
+}
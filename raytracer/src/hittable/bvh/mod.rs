@@ -1,105 +1,132 @@
 pub mod aabb;
+pub mod linear;
+pub mod split;
+pub mod wide;
 
-use rand::Rng;
-use std::cmp::Ordering;
+use std::fmt;
 use std::sync::Arc;
 
 use crate::basic::ray::Ray;
-use crate::hittable::{Hittable, HittableList};
+use crate::hittable::{HitRecord, Hittable, HittableList};
 use aabb::AABB;
+use linear::LinearBvh;
+use split::SplitMethod;
+use wide::{Bvh4, Ray4, PADDING};
 
+#[derive(Debug)]
+pub enum BvhError {
+    Empty,
+    // Index of an object whose box has NaN or inverted bounds.
+    InvalidBox(usize),
+}
+
+impl fmt::Display for BvhError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BvhError::Empty => write!(f, "no objects to build a BVH over"),
+            BvhError::InvalidBox(index) => {
+                write!(f, "object {} has an invalid bounding box", index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BvhError {}
+
+// A BVH over any objects, kept as a flat array of 4-wide nodes whose leaves index
+// into `objects`, and traversed without recursion. Objects without a bounding box,
+// like infinite planes, are kept aside and tested against every ray.
 #[derive(Clone)]
 pub struct BvhNode {
-    aabbox: AABB,
-    left: Option<Arc<dyn Hittable>>,
-    right: Option<Arc<dyn Hittable>>,
+    objects: Vec<Arc<dyn Hittable>>,
+    bvh: Bvh4,
+    unbounded: Vec<Arc<dyn Hittable>>,
 }
 
 impl BvhNode {
-    pub fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
-        let box_a = a.bounding_box(0., 0.).unwrap();
-        let box_b = b.bounding_box(0., 0.).unwrap();
-        if box_a.min[axis] < box_b.min[axis] {
-            Ordering::Less
-        } else if box_a.min[axis] > box_b.min[axis] {
-            Ordering::Greater
-        } else {
-            Ordering::Equal
-        }
-    }
-    pub fn new_list(list: &HittableList, time0: f64, time1: f64) -> Self {
+    pub fn new_list(list: &HittableList, time0: f64, time1: f64) -> Result<Self, BvhError> {
         BvhNode::new_vec(list.objects.clone(), time0, time1)
     }
-    #[allow(unused_assignments)]
-    pub fn new_vec(mut objects: Vec<Arc<dyn Hittable>>, time0: f64, time1: f64) -> Self {
-        let axis: usize = rand::thread_rng().gen_range(0..3);
-
-        let objects_span = objects.len();
-        let mut left: Option<Arc<dyn Hittable>> = None;
-        let mut right: Option<Arc<dyn Hittable>> = None;
 
-        if objects_span == 0 {
-            panic!("BvhNode::new_vec: Get empty vec");
-        }
-        if objects_span == 1 {
-            let obj0 = objects.pop().unwrap();
-            left = Some(obj0.clone());
-            right = Some(obj0);
-        } else if objects_span == 2 {
-            let obj0 = objects.pop().unwrap();
-            let obj1 = objects.pop().unwrap();
-            if BvhNode::box_compare(&obj0, &obj1, axis) == Ordering::Less {
-                left = Some(obj0);
-                right = Some(obj1);
-            } else {
-                left = Some(obj1);
-                right = Some(obj0);
-            }
-        } else {
-            objects.sort_by(|a, b| BvhNode::box_compare(a, b, axis));
-
-            let mut left_vec = objects;
-            let right_vec = left_vec.split_off(objects_span / 2);
+    pub fn new_vec(
+        objects: Vec<Arc<dyn Hittable>>,
+        time0: f64,
+        time1: f64,
+    ) -> Result<Self, BvhError> {
+        BvhNode::with_method(objects, time0, time1, SplitMethod::Sah)
+    }
 
-            left = Some(Arc::new(BvhNode::new_vec(left_vec, time0, time1)));
-            right = Some(Arc::new(BvhNode::new_vec(right_vec, time0, time1)));
+    pub fn with_method(
+        objects: Vec<Arc<dyn Hittable>>,
+        time0: f64,
+        time1: f64,
+        method: SplitMethod,
+    ) -> Result<Self, BvhError> {
+        if objects.is_empty() {
+            return Err(BvhError::Empty);
         }
-
-        if let Some(left_box) = left.as_ref().unwrap().bounding_box(time0, time1) {
-            if let Some(right_box) = right.as_ref().unwrap().bounding_box(time0, time1) {
-                Self {
-                    aabbox: AABB::surrounding_box(left_box, right_box),
-                    left,
-                    right,
+        let mut bounded = Vec::with_capacity(objects.len());
+        let mut boxes = Vec::with_capacity(objects.len());
+        let mut unbounded = Vec::new();
+        for (index, object) in objects.into_iter().enumerate() {
+            match object.bounding_box(time0, time1) {
+                Some(aabbox) => {
+                    // Also false for NaN
+                    if !(0..3).all(|a| aabbox.min[a] <= aabbox.max[a]) {
+                        return Err(BvhError::InvalidBox(index));
+                    }
+                    bounded.push(object);
+                    boxes.push(aabbox);
                 }
-            } else {
-                panic!("BvhNode::new_vec: No bounding box in bvh_node constructor.");
+                None => unbounded.push(object),
             }
-        } else {
-            panic!("BvhNode::new_vec: No bounding box in bvh_node constructor.");
         }
+        Ok(Self {
+            objects: bounded,
+            bvh: Bvh4::new(&LinearBvh::with_method(&boxes, method)),
+            unbounded,
+        })
+    }
+
+    // Box around the objects which have one, even if others are unbounded.
+    pub fn bounded_box(&self) -> Option<AABB> {
+        self.bvh.bounds()
     }
 }
 
 impl Hittable for BvhNode {
-    #[allow(clippy::manual_map)]
-    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<crate::hittable::HitRecord> {
-        if !self.aabbox.hit(r, t_min, t_max) {
-            return None;
-        }
-        if let Some(recl) = self.left.as_ref().unwrap().hit(r, t_min, t_max) {
-            if let Some(recr) = self.right.as_ref().unwrap().hit(r, t_min, recl.t) {
-                Some(recr)
-            } else {
-                Some(recl)
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut closest: Option<HitRecord> = None;
+        for object in &self.unbounded {
+            let t_closest = closest.as_ref().map_or(t_max, |rec| rec.t);
+            if let Some(rec) = object.hit(r, t_min, t_closest) {
+                closest = Some(rec);
             }
-        } else if let Some(recr) = self.right.as_ref().unwrap().hit(r, t_min, t_max) {
-            Some(recr)
-        } else {
-            None
         }
+        let t_closest = closest.as_ref().map_or(t_max, |rec| rec.t);
+        self.bvh
+            .hit(&Ray4::new(r), t_min, t_closest, |range, mut t_max| {
+                let mut hit = None;
+                for &index in &self.bvh.order[range] {
+                    if index == PADDING {
+                        continue;
+                    }
+                    if let Some(rec) = self.objects[index as usize].hit(r, t_min, t_max) {
+                        t_max = rec.t;
+                        hit = Some(rec.t);
+                        closest = Some(rec);
+                    }
+                }
+                hit
+            });
+        closest
     }
+
     fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
-        Some(self.aabbox)
+        if self.unbounded.is_empty() {
+            self.bounded_box()
+        } else {
+            None
+        }
     }
 }
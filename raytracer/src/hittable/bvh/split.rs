@@ -0,0 +1,151 @@
+use std::cmp::Ordering;
+
+use super::aabb::AABB;
+
+const BUCKETS: usize = 12;
+// Cost of testing a node's box, against testing one of its primitives.
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SplitMethod {
+    // Halves the primitives at the median centroid along the longest axis.
+    EqualCounts,
+    // Binned surface area heuristic, over all three axes.
+    Sah,
+}
+
+// Reorders the primitives of a node so that the first `mid` go to one child and the
+// rest to the other, and returns (axis, mid). Returns None when they are at most
+// `max_leaf_size` and splitting them does not pay off.
+pub fn split<T, F>(
+    items: &mut [T],
+    aabbox: F,
+    method: SplitMethod,
+    max_leaf_size: usize,
+) -> Option<(usize, usize)>
+where
+    F: Fn(&T) -> AABB,
+{
+    let n = items.len();
+    if n <= 1 {
+        return None;
+    }
+    let mut node_box = aabbox(&items[0]);
+    let mut min = node_box.centroid();
+    let mut max = min;
+    for item in items.iter() {
+        let b = aabbox(item);
+        node_box = AABB::surrounding_box(node_box, b);
+        let c = b.centroid();
+        for a in 0..3 {
+            min[a] = min[a].min(c[a]);
+            max[a] = max[a].max(c[a]);
+        }
+    }
+    let extent = max - min;
+    let longest = if extent.x > extent.y && extent.x > extent.z {
+        0
+    } else if extent.y > extent.z {
+        1
+    } else {
+        2
+    };
+    if extent[longest] <= 0. {
+        if n <= max_leaf_size {
+            return None;
+        }
+        // Nested objects around one center, like the rings of a planet, are told
+        // apart by their size instead
+        items.sort_by(|a, b| {
+            aabbox(a)
+                .surface_area()
+                .partial_cmp(&aabbox(b).surface_area())
+                .unwrap_or(Ordering::Equal)
+        });
+        return Some((longest, n / 2));
+    }
+
+    let sort_and_halve = |items: &mut [T]| {
+        items.sort_by(|a, b| {
+            aabbox(a).centroid()[longest]
+                .partial_cmp(&aabbox(b).centroid()[longest])
+                .unwrap_or(Ordering::Equal)
+        });
+        Some((longest, n / 2))
+    };
+    if method == SplitMethod::EqualCounts {
+        if n <= max_leaf_size {
+            return None;
+        }
+        return sort_and_halve(items);
+    }
+
+    let bucket = |b: AABB, axis: usize| {
+        let offset = (b.centroid()[axis] - min[axis]) / extent[axis];
+        ((offset * BUCKETS as f64) as usize).min(BUCKETS - 1)
+    };
+    let node_area = node_box.surface_area().max(f64::MIN_POSITIVE);
+    // (cost, axis, last bucket of the first child)
+    let mut best = (f64::INFINITY, longest, 0);
+    for axis in 0..3 {
+        if extent[axis] <= 0. {
+            continue;
+        }
+        let mut counts = [0usize; BUCKETS];
+        let mut boxes: [Option<AABB>; BUCKETS] = [None; BUCKETS];
+        for item in items.iter() {
+            let b = aabbox(item);
+            let k = bucket(b, axis);
+            counts[k] += 1;
+            boxes[k] = Some(boxes[k].map_or(b, |acc| AABB::surrounding_box(acc, b)));
+        }
+
+        // Area and count of everything after each bucket, swept from the right
+        let mut right_area = [0.; BUCKETS];
+        let mut right_count = [0usize; BUCKETS];
+        let mut acc: Option<AABB> = None;
+        let mut count = 0;
+        for k in (1..BUCKETS).rev() {
+            if let Some(b) = boxes[k] {
+                acc = Some(acc.map_or(b, |acc| AABB::surrounding_box(acc, b)));
+            }
+            count += counts[k];
+            right_area[k - 1] = acc.map_or(0., |b| b.surface_area());
+            right_count[k - 1] = count;
+        }
+
+        let mut acc: Option<AABB> = None;
+        let mut count = 0;
+        for k in 0..BUCKETS - 1 {
+            if let Some(b) = boxes[k] {
+                acc = Some(acc.map_or(b, |acc| AABB::surrounding_box(acc, b)));
+            }
+            count += counts[k];
+            if count == 0 || right_count[k] == 0 {
+                continue;
+            }
+            let left_area = acc.map_or(0., |b| b.surface_area());
+            let cost = TRAVERSAL_COST
+                + (count as f64 * left_area + right_count[k] as f64 * right_area[k]) / node_area;
+            if cost < best.0 {
+                best = (cost, axis, k);
+            }
+        }
+    }
+
+    let (cost, axis, last) = best;
+    if n <= max_leaf_size && cost >= n as f64 {
+        return None;
+    }
+    if cost.is_infinite() {
+        return sort_and_halve(items);
+    }
+    let mut mid = 0;
+    for i in 0..n {
+        if bucket(aabbox(&items[i]), axis) <= last {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    Some((axis, mid))
+}
@@ -0,0 +1,257 @@
+use std::ops::Range;
+
+use crate::basic::{
+    float::{gamma, round_down, round_up, Float, FloatRay},
+    ray::Ray,
+    simd::F4,
+};
+
+use super::aabb::AABB;
+use super::linear::LinearBvh;
+
+const STACK_SIZE: usize = 64;
+// Every inner node on the way down leaves at most three children on the stack.
+const MAX_DEPTH: usize = (STACK_SIZE - 1) / 3;
+// Entry of `order` filling leaves up to a multiple of four primitives.
+pub const PADDING: u32 = u32::MAX;
+
+// Four child boxes side by side, one per lane. An inner child (count 0) is given by
+// its node index, a leaf by the first of its `count` entries in `order`.
+#[derive(Clone, Copy)]
+pub struct Node4 {
+    min: [[Float; 4]; 3],
+    max: [[Float; 4]; 3],
+    child: [u32; 4],
+    count: [u32; 4],
+    // Bit i is set when lane i holds a child.
+    valid: u32,
+}
+
+// A ray with each coordinate copied into all four lanes.
+pub struct Ray4 {
+    pub orig: [F4; 3],
+    pub dir: [F4; 3],
+    pub inv_dir: [F4; 3],
+    pub dir_is_neg: [usize; 3],
+}
+
+impl Ray4 {
+    pub fn new(r: Ray) -> Self {
+        let r = FloatRay::new(r);
+        let splat = |v: [Float; 3]| [F4::splat(v[0]), F4::splat(v[1]), F4::splat(v[2])];
+        Self {
+            orig: splat([r.orig.x, r.orig.y, r.orig.z]),
+            dir: splat([r.dir.x, r.dir.y, r.dir.z]),
+            inv_dir: splat([r.inv_dir.x, r.inv_dir.y, r.inv_dir.z]),
+            dir_is_neg: [
+                r.dir_is_neg[0] as usize,
+                r.dir_is_neg[1] as usize,
+                r.dir_is_neg[2] as usize,
+            ],
+        }
+    }
+}
+
+// A BVH with four children per node, collapsed from a binary one, so that a ray is
+// tested against all four boxes at once. Leaves start at a multiple of four in
+// `order` and are padded with `PADDING` to one, so they can be packed four wide too.
+#[derive(Clone)]
+pub struct Bvh4 {
+    pub nodes: Vec<Node4>,
+    pub order: Vec<u32>,
+    bounds: Option<AABB>,
+}
+
+enum Slot {
+    Inner(usize),
+    Leaf(usize, usize),
+}
+
+impl Bvh4 {
+    pub fn new(bvh: &LinearBvh) -> Self {
+        let mut wide = Self {
+            nodes: Vec::with_capacity(bvh.nodes.len() / 2 + 1),
+            order: Vec::with_capacity(bvh.order.len() * 2),
+            bounds: bvh.bounds(),
+        };
+        // Primitives under each node, children come after their parent
+        let mut sizes = vec![0; bvh.nodes.len()];
+        for (index, node) in bvh.nodes.iter().enumerate().rev() {
+            sizes[index] = if node.count > 0 {
+                node.count as usize
+            } else {
+                sizes[index + 1] + sizes[node.offset as usize]
+            };
+        }
+        if !bvh.nodes.is_empty() {
+            wide.build(bvh, &sizes, 0, 0);
+        }
+        wide
+    }
+
+    // Box around all the primitives.
+    pub fn bounds(&self) -> Option<AABB> {
+        self.bounds
+    }
+
+    // Keeps opening the largest inner node among the children of `index` until there
+    // are four of them. Nodes with at most four primitives below become one leaf, so
+    // that leaves fill the four lanes of a pack as much as they can. Past `MAX_DEPTH`
+    // whole subtrees become leaves, to keep within the traversal stack.
+    fn build(&mut self, bvh: &LinearBvh, sizes: &[usize], index: usize, depth: usize) -> usize {
+        let is_leaf = |index: usize| {
+            bvh.nodes[index].count > 0 || sizes[index] <= 4 || depth + 1 >= MAX_DEPTH
+        };
+        let mut children = if bvh.nodes[index].count > 0 || sizes[index] <= 4 {
+            vec![index]
+        } else {
+            vec![index + 1, bvh.nodes[index].offset as usize]
+        };
+        while children.len() < 4 {
+            let largest = children
+                .iter()
+                .enumerate()
+                .filter(|(_, &child)| !is_leaf(child))
+                .map(|(i, &child)| (i, AABB::from(bvh.nodes[child].aabbox).surface_area()))
+                .fold(None, |best: Option<(usize, f64)>, (i, area)| match best {
+                    Some((_, best_area)) if best_area >= area => best,
+                    _ => Some((i, area)),
+                });
+            match largest {
+                Some((i, _)) => {
+                    let child = children.swap_remove(i);
+                    children.push(child + 1);
+                    children.push(bvh.nodes[child].offset as usize);
+                }
+                None => break,
+            }
+        }
+
+        let node_index = self.nodes.len();
+        self.nodes.push(Node4 {
+            min: [[0.; 4]; 3],
+            max: [[0.; 4]; 3],
+            child: [0; 4],
+            count: [0; 4],
+            valid: 0,
+        });
+        let slots: Vec<Slot> = children
+            .iter()
+            .map(|&child| {
+                if is_leaf(child) {
+                    let offset = self.order.len();
+                    self.gather(bvh, child);
+                    while self.order.len() % 4 != 0 {
+                        self.order.push(PADDING);
+                    }
+                    Slot::Leaf(offset, self.order.len() - offset)
+                } else {
+                    Slot::Inner(self.build(bvh, sizes, child, depth + 1))
+                }
+            })
+            .collect();
+
+        let node = &mut self.nodes[node_index];
+        for (lane, (&child, slot)) in children.iter().zip(slots.iter()).enumerate() {
+            let aabbox = bvh.nodes[child].aabbox;
+            for axis in 0..3 {
+                node.min[axis][lane] = aabbox.min[axis];
+                node.max[axis][lane] = aabbox.max[axis];
+            }
+            match *slot {
+                Slot::Inner(index) => node.child[lane] = index as u32,
+                Slot::Leaf(offset, count) => {
+                    node.child[lane] = offset as u32;
+                    node.count[lane] = count as u32;
+                }
+            }
+            node.valid |= 1 << lane;
+        }
+        node_index
+    }
+
+    // Appends the primitives under `index` to `order`.
+    fn gather(&mut self, bvh: &LinearBvh, index: usize) {
+        let node = &bvh.nodes[index];
+        if node.count > 0 {
+            let begin = node.offset as usize;
+            self.order
+                .extend_from_slice(&bvh.order[begin..begin + node.count as usize]);
+        } else {
+            self.gather(bvh, index + 1);
+            self.gather(bvh, node.offset as usize);
+        }
+    }
+
+    // Visits the leaves whose boxes are hit, nearest box first. `hit_leaf` gets a range
+    // of `order` and the current `t_max`, and returns the distance of a closer hit.
+    pub fn hit<F>(&self, r: &Ray4, t_min: f64, t_max: f64, mut hit_leaf: F) -> bool
+    where
+        F: FnMut(Range<usize>, f64) -> Option<f64>,
+    {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let widen = F4::splat((1. + 2. * gamma(3)) as Float);
+        let t_min_f = F4::splat(round_down(t_min));
+        let mut closest_so_far = t_max;
+        let mut hit_anything = false;
+        // (child, count, distance to its box)
+        let mut stack = [(0u32, 0u32, 0 as Float); STACK_SIZE];
+        let mut stack_size = 1;
+        stack[0] = (0, 0, round_down(t_min));
+        while stack_size > 0 {
+            stack_size -= 1;
+            let (child, count, near) = stack[stack_size];
+            if near as f64 > closest_so_far {
+                continue;
+            }
+            if count > 0 {
+                let begin = child as usize;
+                if let Some(t) = hit_leaf(begin..begin + count as usize, closest_so_far) {
+                    closest_so_far = t;
+                    hit_anything = true;
+                }
+                continue;
+            }
+
+            let node = &self.nodes[child as usize];
+            let bounds = [&node.min, &node.max];
+            let mut t_near = t_min_f;
+            let mut t_far = F4::splat(round_up(closest_so_far));
+            for (axis, &neg) in r.dir_is_neg.iter().enumerate() {
+                let t0 = (F4::load(&bounds[neg][axis]) - r.orig[axis]) * r.inv_dir[axis];
+                let t1 = (F4::load(&bounds[1 - neg][axis]) - r.orig[axis]) * r.inv_dir[axis];
+                // The new distance goes first, so a NaN from a ray lying in the slab
+                // plane leaves the range alone
+                t_near = F4::max(t0, t_near);
+                t_far = F4::min(t1 * widen, t_far);
+            }
+            let mask = F4::le(t_near, t_far) & node.valid;
+            if mask == 0 {
+                continue;
+            }
+
+            // Pushed farthest first, so the nearest child is visited next
+            let t_near = t_near.to_array();
+            let mut hits = [(0 as Float, 0usize); 4];
+            let mut hit_count = 0;
+            for (lane, &near) in t_near.iter().enumerate() {
+                if mask & 1 << lane != 0 {
+                    let mut i = hit_count;
+                    while i > 0 && hits[i - 1].0 < near {
+                        hits[i] = hits[i - 1];
+                        i -= 1;
+                    }
+                    hits[i] = (near, lane);
+                    hit_count += 1;
+                }
+            }
+            for &(near, lane) in &hits[..hit_count] {
+                stack[stack_size] = (node.child[lane], node.count[lane], near);
+                stack_size += 1;
+            }
+        }
+        hit_anything
+    }
+}
@@ -0,0 +1,117 @@
+use std::f64::INFINITY;
+
+use crate::{
+    basic::ray::Ray,
+    hittable::bvh::aabb::AABB,
+    hittable::{HitRecord, Hittable},
+};
+
+// Distance moved past a surface before looking for the next one.
+const CSG_EPS: f64 = 1e-6;
+
+#[derive(Clone, Copy)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(&self, in_a: bool, in_b: bool) -> bool {
+        match self {
+            CsgOp::Union => in_a || in_b,
+            CsgOp::Intersection => in_a && in_b,
+            CsgOp::Difference => in_a && !in_b,
+        }
+    }
+}
+
+// A solid combined from two closed objects, whose hits must have `front_face` set
+// when the ray enters them. Each surface keeps the material of the object it
+// comes from.
+pub struct Csg<A, B>
+where
+    A: Hittable,
+    B: Hittable,
+{
+    a: A,
+    b: B,
+    op: CsgOp,
+}
+
+impl<A: Hittable, B: Hittable> Csg<A, B> {
+    pub fn new(a: A, b: B, op: CsgOp) -> Self {
+        Self { a, b, op }
+    }
+
+    #[allow(dead_code)]
+    pub fn union(a: A, b: B) -> Self {
+        Self::new(a, b, CsgOp::Union)
+    }
+
+    pub fn intersection(a: A, b: B) -> Self {
+        Self::new(a, b, CsgOp::Intersection)
+    }
+
+    // `a` with `b` carved out of it.
+    pub fn difference(a: A, b: B) -> Self {
+        Self::new(a, b, CsgOp::Difference)
+    }
+}
+
+impl<A: Hittable, B: Hittable> Hittable for Csg<A, B> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // Whether the ray starts inside a solid is only known from how it leaves
+        // it, so both are followed past `t_max` if needed
+        let mut hit_a = self.a.hit(r, t_min, INFINITY);
+        let mut hit_b = self.b.hit(r, t_min, INFINITY);
+        let mut in_a = hit_a.map_or(false, |rec| !rec.front_face);
+        let mut in_b = hit_b.map_or(false, |rec| !rec.front_face);
+
+        loop {
+            let (mut rec, from_a) = match (hit_a, hit_b) {
+                (Some(rec_a), Some(rec_b)) => {
+                    if rec_a.t <= rec_b.t {
+                        (rec_a, true)
+                    } else {
+                        (rec_b, false)
+                    }
+                }
+                (Some(rec_a), None) => (rec_a, true),
+                (None, Some(rec_b)) => (rec_b, false),
+                (None, None) => return None,
+            };
+            if rec.t > t_max {
+                return None;
+            }
+
+            let was_inside = self.op.inside(in_a, in_b);
+            if from_a {
+                in_a = rec.front_face;
+                hit_a = self.a.hit(r, rec.t + CSG_EPS, INFINITY);
+            } else {
+                in_b = rec.front_face;
+                hit_b = self.b.hit(r, rec.t + CSG_EPS, INFINITY);
+            }
+            let inside = self.op.inside(in_a, in_b);
+            if inside != was_inside {
+                // `normal` already faces the ray, only the side changes
+                rec.front_face = inside;
+                return Some(rec);
+            }
+        }
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        let box_a = self.a.bounding_box(time0, time1);
+        let box_b = self.b.bounding_box(time0, time1);
+        match self.op {
+            CsgOp::Union => Some(AABB::surrounding_box(box_a?, box_b?)),
+            CsgOp::Intersection => match (box_a, box_b) {
+                (Some(box_a), Some(box_b)) => Some(AABB::overlap_box(box_a, box_b)),
+                _ => box_a.or(box_b),
+            },
+            CsgOp::Difference => box_a,
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{
+    basic::{
+        onb::Onb,
+        ray::Ray,
+        vec::{Point3, Vec3},
+    },
+    material::Material,
+};
+
+use super::{bvh::aabb::AABB, quadric::Frame, HitRecord, Hittable};
+
+const MAX_SUBDIVISIONS: i32 = 10;
+
+#[derive(Clone, Copy)]
+pub enum CurveMode {
+    // A flat strip which always faces the ray, for grass blades and the like.
+    Ribbon,
+    // Still a strip facing the ray, but shaded as if it were a round tube, for
+    // hair and fur.
+    Cylinder,
+}
+
+// A cubic Bezier curve whose width goes linearly from `width0` to `width1`.
+// `u` runs along the curve and `v` across it.
+pub struct Curve<M>
+where
+    M: Material,
+{
+    cp: [Point3; 4],
+    width0: f64,
+    width1: f64,
+    mode: CurveMode,
+    mp: M,
+}
+
+impl<M: Material> Curve<M> {
+    pub fn new(cp: [Point3; 4], width0: f64, width1: f64, mode: CurveMode, mp: M) -> Self {
+        Self {
+            cp,
+            width0,
+            width1,
+            mode,
+            mp,
+        }
+    }
+
+    fn width(&self, u: f64) -> f64 {
+        self.width0 + (self.width1 - self.width0) * u
+    }
+
+    // Finds the closest hit with the curve in ray space, where the ray starts at
+    // the origin and goes along z at unit speed. Returns (t, u).
+    #[allow(clippy::too_many_arguments)]
+    fn intersect(
+        &self,
+        cp: &[Point3; 4],
+        u0: f64,
+        u1: f64,
+        depth: i32,
+        t_min: f64,
+        t_max: f64,
+        closest: &mut Option<(f64, f64)>,
+    ) {
+        let t_max = closest.map_or(t_max, |(t, _)| t);
+        let half_width = 0.5 * self.width(u0).max(self.width(u1));
+        let (mut min, mut max) = (cp[0], cp[0]);
+        for p in cp.iter().skip(1) {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        if min.x - half_width > 0.
+            || max.x + half_width < 0.
+            || min.y - half_width > 0.
+            || max.y + half_width < 0.
+            || max.z + half_width < t_min
+            || min.z - half_width > t_max
+        {
+            return;
+        }
+
+        if depth > 0 {
+            let (left, right) = split_bezier(cp);
+            let mid = 0.5 * (u0 + u1);
+            self.intersect(&left, u0, mid, depth - 1, t_min, t_max, closest);
+            self.intersect(&right, mid, u1, depth - 1, t_min, t_max, closest);
+            return;
+        }
+
+        // The ray must pass between the planes perpendicular to the curve at
+        // both ends of this piece
+        let start = (cp[1].y - cp[0].y) * -cp[0].y + cp[0].x * (cp[0].x - cp[1].x);
+        let end = (cp[2].y - cp[3].y) * -cp[3].y + cp[3].x * (cp[3].x - cp[2].x);
+        if start < 0. || end < 0. {
+            return;
+        }
+
+        // Closest point to the ray, taking the piece as a straight segment
+        let (dx, dy) = (cp[3].x - cp[0].x, cp[3].y - cp[0].y);
+        let denom = dx * dx + dy * dy;
+        if denom == 0. {
+            return;
+        }
+        let w = ((-cp[0].x * dx - cp[0].y * dy) / denom).clamp(0., 1.);
+        let u = u0 + (u1 - u0) * w;
+        let pc = eval_bezier(cp, w).0;
+        let hit_width = self.width(u);
+        if pc.x * pc.x + pc.y * pc.y > 0.25 * hit_width * hit_width {
+            return;
+        }
+        if pc.z < t_min || pc.z > t_max {
+            return;
+        }
+        *closest = Some((pc.z, u));
+    }
+}
+
+// The point and the derivative at `u`, by de Casteljau's algorithm.
+fn eval_bezier(cp: &[Point3; 4], u: f64) -> (Point3, Vec3) {
+    let lerp = |a: Point3, b: Point3| a * (1. - u) + b * u;
+    let cp1 = [lerp(cp[0], cp[1]), lerp(cp[1], cp[2]), lerp(cp[2], cp[3])];
+    let cp2 = [lerp(cp1[0], cp1[1]), lerp(cp1[1], cp1[2])];
+    let derivative = if (cp2[1] - cp2[0]).length_sqr() > 0. {
+        (cp2[1] - cp2[0]) * 3.
+    } else {
+        // Doubled control points at the ends
+        cp[3] - cp[0]
+    };
+    (lerp(cp2[0], cp2[1]), derivative)
+}
+
+fn split_bezier(cp: &[Point3; 4]) -> ([Point3; 4], [Point3; 4]) {
+    let mid = |a: Point3, b: Point3| (a + b) * 0.5;
+    let (m01, m12, m23) = (mid(cp[0], cp[1]), mid(cp[1], cp[2]), mid(cp[2], cp[3]));
+    let (m012, m123) = (mid(m01, m12), mid(m12, m23));
+    let center = mid(m012, m123);
+    ([cp[0], m01, m012, center], [center, m123, m23, cp[3]])
+}
+
+impl<M: Material> Hittable for Curve<M> {
+    #[allow(clippy::many_single_char_names)]
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let len = r.dir.length();
+        let frame = Frame::new(r.orig, r.dir);
+        let cp = [
+            frame.point_to_local(self.cp[0]),
+            frame.point_to_local(self.cp[1]),
+            frame.point_to_local(self.cp[2]),
+            frame.point_to_local(self.cp[3]),
+        ];
+
+        // Enough subdivisions for the pieces to be about straight at this width
+        let mut l0: f64 = 0.;
+        for i in 0..2 {
+            let d = cp[i] - cp[i + 1] * 2. + cp[i + 2];
+            l0 = l0.max(d.x.abs()).max(d.y.abs()).max(d.z.abs());
+        }
+        let eps = 0.05 * self.width0.max(self.width1);
+        let depth = if l0 > 0. && eps > 0. {
+            ((2f64.sqrt() * 6. * l0 / (8. * eps)).log2() / 2.)
+                .round()
+                .clamp(0., MAX_SUBDIVISIONS as f64) as i32
+        } else {
+            0
+        };
+
+        let mut closest = None;
+        self.intersect(&cp, 0., 1., depth, t_min * len, t_max * len, &mut closest);
+        let (t, u) = closest?;
+        let t = t / len;
+
+        let p = r.at(t);
+        let (center, dpdu) = eval_bezier(&self.cp, u);
+        let unit_dir = r.dir / len;
+        let tangent = dpdu.to_unit();
+        let mut side = Vec3::cross(tangent, unit_dir);
+        side = if side.length_sqr() > 0. {
+            side.to_unit()
+        } else {
+            Onb::build_from_w(tangent).u()
+        };
+        let mut facing = Vec3::cross(side, tangent);
+        if Vec3::dot(facing, unit_dir) > 0. {
+            facing = -facing;
+        }
+
+        let hit_width = self.width(u);
+        let v = if hit_width > 0. {
+            (0.5 + Vec3::dot(p - center, side) / hit_width).clamp(0., 1.)
+        } else {
+            0.5
+        };
+        let mut rec = HitRecord::new(p, facing, t, u, v, true, &self.mp);
+        rec.set_face_normal(r, facing);
+        rec.dpdu = dpdu;
+        if let CurveMode::Cylinder = self.mode {
+            // Turn the normal towards the edges as if the strip were round
+            let theta = (v - 0.5) * std::f64::consts::PI;
+            rec.set_shading_normal(r, facing * theta.cos() + side * theta.sin());
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        let (mut min, mut max) = (self.cp[0], self.cp[0]);
+        for p in self.cp.iter().skip(1) {
+            for i in 0..3 {
+                min[i] = min[i].min(p[i]);
+                max[i] = max[i].max(p[i]);
+            }
+        }
+        let half_width = 0.5 * self.width0.max(self.width1);
+        let padding = Vec3::new(half_width, half_width, half_width);
+        Some(AABB::new(min - padding, max + padding))
+    }
+}
@@ -0,0 +1,231 @@
+use std::f64::INFINITY;
+
+use image::error::{ImageError, ParameterError, ParameterErrorKind};
+
+use crate::{
+    basic::{
+        float::FloatRay,
+        ray::Ray,
+        vec::{Point3, Vec3},
+    },
+    material::Material,
+};
+
+use super::{bvh::aabb::AABB, mesh::MeshData, HitRecord, Hittable};
+
+// A grid of heights over the xz plane, split into two triangles per cell.
+// `u` grows with x and `v` with z, so the image it was made from can be used
+// as its texture.
+pub struct Heightfield<M>
+where
+    M: Material,
+{
+    data: MeshData,
+    nx: usize,
+    nz: usize,
+    corner: Point3,
+    dx: f64,
+    dz: f64,
+    // Lowest and highest point of each cell, to skip cells the ray passes over.
+    cell_range: Vec<(f64, f64)>,
+    aabbox: AABB,
+    mp: M,
+}
+
+impl<M: Material> Heightfield<M> {
+    // Gray level 0 is at `center.y` and 255 is `height_scale` above it.
+    #[allow(dead_code)]
+    pub fn open(
+        file_name: &str,
+        center: Point3,
+        width: f64,
+        depth: f64,
+        height_scale: f64,
+        mp: M,
+    ) -> image::ImageResult<Self> {
+        let img = image::open(file_name)?.to_luma8();
+        let (nx, nz) = (img.width() as usize, img.height() as usize);
+        // The top row of the image is at the far end, where v = 1
+        let mut heights = vec![0.; nx * nz];
+        for (i, j, pixel) in img.enumerate_pixels() {
+            let row = nz - 1 - j as usize;
+            heights[row * nx + i as usize] = pixel.0[0] as f64 / 255. * height_scale;
+        }
+        Self::from_heights(nx, nz, &heights, center, width, depth, mp)
+    }
+
+    // `heights` are given row by row from the lowest z. Fails unless there are
+    // `nx * nz` of them, with at least two rows of two.
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_heights(
+        nx: usize,
+        nz: usize,
+        heights: &[f64],
+        center: Point3,
+        width: f64,
+        depth: f64,
+        mp: M,
+    ) -> image::ImageResult<Self> {
+        // Every cell needs a height at each corner
+        if nx < 2 || nz < 2 || heights.len() != nx * nz {
+            return Err(ImageError::Parameter(ParameterError::from_kind(
+                ParameterErrorKind::DimensionMismatch,
+            )));
+        }
+        let corner = center - Vec3::new(width / 2., 0., depth / 2.);
+        let dx = width / (nx - 1) as f64;
+        let dz = depth / (nz - 1) as f64;
+        let height = |i: usize, j: usize| heights[j * nx + i];
+
+        let mut positions = Vec::with_capacity(nx * nz);
+        let mut normals = Vec::with_capacity(nx * nz);
+        let mut uvs = Vec::with_capacity(nx * nz);
+        for j in 0..nz {
+            for i in 0..nx {
+                positions.push(corner + Vec3::new(i as f64 * dx, height(i, j), j as f64 * dz));
+                // Central differences, one-sided at the borders
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(nx - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(nz - 1));
+                let slope_x = (height(i1, j) - height(i0, j)) / ((i1 - i0) as f64 * dx);
+                let slope_z = (height(i, j1) - height(i, j0)) / ((j1 - j0) as f64 * dz);
+                normals.push(Vec3::new(-slope_x, 1., -slope_z).to_unit().into());
+                uvs.push((i as f64 / (nx - 1) as f64, j as f64 / (nz - 1) as f64));
+            }
+        }
+
+        let mut indices = Vec::with_capacity(2 * (nx - 1) * (nz - 1));
+        let mut cell_range = Vec::with_capacity((nx - 1) * (nz - 1));
+        for j in 0..nz - 1 {
+            for i in 0..nx - 1 {
+                let a = (j * nx + i) as u32;
+                let b = a + 1;
+                let c = a + nx as u32;
+                let d = c + 1;
+                indices.push([a, c, d]);
+                indices.push([a, d, b]);
+                let corners = [
+                    corner.y + height(i, j),
+                    corner.y + height(i + 1, j),
+                    corner.y + height(i, j + 1),
+                    corner.y + height(i + 1, j + 1),
+                ];
+                cell_range.push((
+                    corners.iter().copied().fold(INFINITY, f64::min),
+                    corners.iter().copied().fold(-INFINITY, f64::max),
+                ));
+            }
+        }
+
+        let low = cell_range
+            .iter()
+            .map(|range| range.0)
+            .fold(INFINITY, f64::min);
+        let high = cell_range
+            .iter()
+            .map(|range| range.1)
+            .fold(-INFINITY, f64::max);
+        let thickness = 0.0001;
+        let aabbox = AABB::new(
+            Point3::new(corner.x, low - thickness, corner.z),
+            Point3::new(corner.x + width, high + thickness, corner.z + depth),
+        );
+
+        let mut data = MeshData::new(positions, indices);
+        data.normals = normals;
+        data.uvs = uvs;
+        Ok(Self {
+            data,
+            nx,
+            nz,
+            corner,
+            dx,
+            dz,
+            cell_range,
+            aabbox,
+            mp,
+        })
+    }
+}
+
+impl<M: Material> Hittable for Heightfield<M> {
+    // Walks the cells under the ray front to back, as a 2D DDA.
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_start, t_end) = self.aabbox.clip(r, t_min, t_max)?;
+        let (cells_x, cells_z) = (self.nx - 1, self.nz - 1);
+
+        let p = r.at(t_start);
+        let cell = |offset: f64, size: f64, count: usize| {
+            ((offset / size).floor().max(0.) as usize).min(count - 1)
+        };
+        let mut i = cell(p.x - self.corner.x, self.dx, cells_x);
+        let mut j = cell(p.z - self.corner.z, self.dz, cells_z);
+
+        // t at which the ray crosses the next cell border along x or z
+        let next_border = |index: usize, dir: f64, orig: f64, corner: f64, size: f64| {
+            if dir > 0. {
+                (corner + (index + 1) as f64 * size - orig) / dir
+            } else if dir < 0. {
+                (corner + index as f64 * size - orig) / dir
+            } else {
+                INFINITY
+            }
+        };
+        let mut t_next_x = next_border(i, r.dir.x, r.orig.x, self.corner.x, self.dx);
+        let mut t_next_z = next_border(j, r.dir.z, r.orig.z, self.corner.z, self.dz);
+        let t_delta_x = (self.dx / r.dir.x).abs();
+        let t_delta_z = (self.dz / r.dir.z).abs();
+
+        let float_r = FloatRay::new(r);
+        let mut t_cell = t_start;
+        loop {
+            let t_leave = t_next_x.min(t_next_z).min(t_end);
+            let (low, high) = self.cell_range[j * cells_x + i];
+            let y0 = r.orig.y + t_cell * r.dir.y;
+            let y1 = r.orig.y + t_leave * r.dir.y;
+            if y0.max(y1) >= low && y0.min(y1) <= high {
+                let mut closest = None;
+                let mut t_closest = t_max;
+                for face in 2 * (j * cells_x + i)..2 * (j * cells_x + i) + 2 {
+                    if let Some((t, b1, b2)) = self.data.intersect(face, &float_r, t_min, t_closest)
+                    {
+                        closest = Some((face, t, b1, b2));
+                        t_closest = t;
+                    }
+                }
+                if let Some((face, t, b1, b2)) = closest {
+                    return Some(self.data.hit_record(face, r, t, b1, b2, &self.mp));
+                }
+            }
+
+            if t_leave >= t_end {
+                return None;
+            }
+            t_cell = t_leave;
+            if t_next_x < t_next_z {
+                if r.dir.x > 0. {
+                    i += 1;
+                } else {
+                    i = i.checked_sub(1)?;
+                }
+                if i >= cells_x {
+                    return None;
+                }
+                t_next_x += t_delta_x;
+            } else {
+                if r.dir.z > 0. {
+                    j += 1;
+                } else {
+                    j = j.checked_sub(1)?;
+                }
+                if j >= cells_z {
+                    return None;
+                }
+                t_next_z += t_delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        Some(self.aabbox)
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use crate::{
+    basic::{
+        ray::Ray,
+        transform::{AnimatedTransform, Quaternion, Transform},
+        vec::{Color, Point3, Vec3},
+    },
+    material::Material,
+};
+
+use super::{bvh::aabb::AABB, HitRecord, Hittable};
+
+// An object placed in the world by an affine transform. With an `Arc<dyn Hittable>`
+// prototype, any number of instances share one copy of its geometry and BVH.
+pub struct Instance<H>
+where
+    H: Hittable,
+{
+    ptr: H,
+    transform: Transform,
+    material: Option<Arc<dyn Material>>,
+}
+
+impl<H: Hittable> Instance<H> {
+    pub fn new(p: H, transform: Transform) -> Self {
+        Self {
+            ptr: p,
+            transform,
+            material: None,
+        }
+    }
+
+    // Hits report `mp` instead of the material of the prototype.
+    pub fn override_material(mut self, mp: Arc<dyn Material>) -> Self {
+        self.material = Some(mp);
+        self
+    }
+
+    // How areas grow under the transform, exact for rotations and uniform scales.
+    fn area_scale(&self) -> f64 {
+        self.transform.determinant().abs().powf(2. / 3.)
+    }
+}
+
+pub fn transform_box(transform: &Transform, aabbox: AABB) -> AABB {
+    let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    let mut max = -min;
+    for i in 0..8 {
+        let corner = Point3::new(
+            if i & 1 == 0 {
+                aabbox.min.x
+            } else {
+                aabbox.max.x
+            },
+            if i & 2 == 0 {
+                aabbox.min.y
+            } else {
+                aabbox.max.y
+            },
+            if i & 4 == 0 {
+                aabbox.min.z
+            } else {
+                aabbox.max.z
+            },
+        );
+        let p = transform.point(corner);
+        for a in 0..3 {
+            min[a] = min[a].min(p[a]);
+            max[a] = max[a].max(p[a]);
+        }
+    }
+    AABB::new(min, max)
+}
+
+pub fn transform_record(transform: &Transform, rec: &mut HitRecord) {
+    // Orientations survive the inverse transpose, so `front_face` stays valid
+    rec.p = transform.point(rec.p);
+    rec.p_error = transform.error(rec.p_error);
+    rec.normal = transform.normal(rec.normal).to_unit();
+    rec.geometric_normal = transform.normal(rec.geometric_normal).to_unit();
+    rec.dpdu = transform.vector(rec.dpdu);
+}
+
+impl<H: Hittable> Hittable for Instance<H> {
+    // The direction is not normalized, so distances along the ray stay the same.
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let inv = self.transform.inverse();
+        let object_r = Ray::new(inv.point(r.orig), inv.vector(r.dir), r.tm);
+        let mut rec = self.ptr.hit(object_r, t_min, t_max)?;
+        transform_record(&self.transform, &mut rec);
+        if let Some(mp) = &self.material {
+            rec.mat_ptr = mp.as_ref();
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        let aabbox = self.ptr.bounding_box(time0, time1)?;
+        Some(transform_box(&self.transform, aabbox))
+    }
+
+    // Light sampling assumes solid angles are kept, i.e. no shear or non-uniform scale.
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        let inv = self.transform.inverse();
+        self.ptr.pdf_value(inv.point(o), inv.vector(v))
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        let inv = self.transform.inverse();
+        self.transform.vector(self.ptr.random(inv.point(o)))
+    }
+
+    fn environment(&self, direction: Vec3) -> Color {
+        self.ptr
+            .environment(self.transform.inverse().vector(direction))
+    }
+
+    // Still the power of the prototype when its material is overridden.
+    fn power(&self) -> f64 {
+        self.ptr.power() * self.area_scale()
+    }
+}
+
+const MOTION_STEPS: usize = 32;
+
+// An object moved by keyframes over the shutter, for motion blur.
+pub struct AnimatedInstance<H>
+where
+    H: Hittable,
+{
+    ptr: H,
+    motion: AnimatedTransform,
+}
+
+impl<H: Hittable> AnimatedInstance<H> {
+    pub fn new(p: H, motion: AnimatedTransform) -> Self {
+        Self { ptr: p, motion }
+    }
+}
+
+impl<H: Hittable> Hittable for AnimatedInstance<H> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let transform = self.motion.at(r.tm);
+        let inv = transform.inverse();
+        let object_r = Ray::new(inv.point(r.orig), inv.vector(r.dir), r.tm);
+        let mut rec = self.ptr.hit(object_r, t_min, t_max)?;
+        transform_record(&transform, &mut rec);
+        Some(rec)
+    }
+
+    // Bounds the boxes at many times of the shutter. Corners move on arcs between
+    // two of them, so the result is padded by the largest gap between arc and chord.
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        let aabbox = self.ptr.bounding_box(time0, time1)?;
+
+        let mut times = vec![time0];
+        for k in &self.motion.keyframes {
+            if k.time > time0 && k.time < time1 {
+                times.push(k.time);
+            }
+        }
+        times.push(time1);
+
+        let mut radius: f64 = 0.;
+        for i in 0..8 {
+            let corner = Vec3::new(
+                if i & 1 == 0 {
+                    aabbox.min.x
+                } else {
+                    aabbox.max.x
+                },
+                if i & 2 == 0 {
+                    aabbox.min.y
+                } else {
+                    aabbox.max.y
+                },
+                if i & 4 == 0 {
+                    aabbox.min.z
+                } else {
+                    aabbox.max.z
+                },
+            );
+            radius = radius.max(corner.length());
+        }
+
+        let mut output_box: Option<AABB> = None;
+        let mut pad: f64 = 0.;
+        for span in times.windows(2) {
+            let mut prev = self.motion.keyframe_at(span[0]);
+            for step in 0..=MOTION_STEPS {
+                let time = span[0] + (span[1] - span[0]) * step as f64 / MOTION_STEPS as f64;
+                let k = self.motion.keyframe_at(time);
+                let moved = transform_box(
+                    &Transform::from_trs(k.translation, k.rotation, k.scale),
+                    aabbox,
+                );
+                output_box = Some(match output_box {
+                    Some(b) => AABB::surrounding_box(b, moved),
+                    None => moved,
+                });
+
+                let scale = [prev.scale, k.scale]
+                    .iter()
+                    .map(|s| s.x.abs().max(s.y.abs()).max(s.z.abs()))
+                    .fold(0., f64::max);
+                let angle = Quaternion::angle(prev.rotation, k.rotation);
+                pad = pad.max(radius * scale * (1. - (angle / 2.).cos()));
+                prev = k;
+            }
+        }
+
+        let output_box = output_box?;
+        let pad = Vec3::new(pad, pad, pad);
+        Some(AABB::new(output_box.min - pad, output_box.max + pad))
+    }
+}
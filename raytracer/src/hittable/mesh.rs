@@ -0,0 +1,351 @@
+use std::sync::Arc;
+
+use crate::{
+    basic::{
+        float::{gamma, round_down, round_up, Float, FloatRay, Vec3f},
+        ray::Ray,
+        simd::{cross, dot, F4},
+        vec::{Color, Point3, Vec3},
+    },
+    material::Material,
+};
+
+use super::{
+    bvh::{
+        aabb::AABB,
+        linear::LinearBvh,
+        wide::{Bvh4, Ray4, PADDING},
+    },
+    HitRecord, Hittable,
+};
+
+// Vertex buffers shared by all the triangles of a mesh.
+// `normals`, `uvs` and `colors` are either empty or have one entry per position.
+#[derive(Clone, Default)]
+pub struct MeshData {
+    pub positions: Vec<Vec3f>,
+    pub normals: Vec<Vec3f>,
+    pub uvs: Vec<(f64, f64)>,
+    pub colors: Vec<Color>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl MeshData {
+    pub fn new(positions: Vec<Point3>, indices: Vec<[u32; 3]>) -> Self {
+        Self {
+            positions: positions.into_iter().map(Vec3f::from).collect(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            indices,
+        }
+    }
+
+    pub fn vertices(&self, face: usize) -> (Point3, Point3, Point3) {
+        let [a, b, c] = self.indices[face];
+        (
+            self.positions[a as usize].into(),
+            self.positions[b as usize].into(),
+            self.positions[c as usize].into(),
+        )
+    }
+
+    // Interpolated vertex normal, if the mesh has usable ones.
+    pub fn normal(&self, face: usize, b1: f64, b2: f64) -> Option<Vec3> {
+        if self.normals.len() != self.positions.len() {
+            return None;
+        }
+        let [a, b, c] = self.indices[face];
+        let n = Vec3::from(self.normals[a as usize]) * (1. - b1 - b2)
+            + Vec3::from(self.normals[b as usize]) * b1
+            + Vec3::from(self.normals[c as usize]) * b2;
+        if n.length_sqr() > 0. {
+            Some(n.to_unit())
+        } else {
+            None
+        }
+    }
+
+    pub fn uv(&self, face: usize, b1: f64, b2: f64) -> Option<(f64, f64)> {
+        if self.uvs.len() != self.positions.len() {
+            return None;
+        }
+        let [a, b, c] = self.indices[face];
+        let (ua, va) = self.uvs[a as usize];
+        let (ub, vb) = self.uvs[b as usize];
+        let (uc, vc) = self.uvs[c as usize];
+        let b0 = 1. - b1 - b2;
+        Some((b0 * ua + b1 * ub + b2 * uc, b0 * va + b1 * vb + b2 * vc))
+    }
+
+    pub fn color(&self, face: usize, b1: f64, b2: f64) -> Option<Color> {
+        if self.colors.len() != self.positions.len() {
+            return None;
+        }
+        let [a, b, c] = self.indices[face];
+        Some(
+            self.colors[a as usize] * (1. - b1 - b2)
+                + self.colors[b as usize] * b1
+                + self.colors[c as usize] * b2,
+        )
+    }
+
+    pub fn dpdu(&self, face: usize) -> Option<Vec3> {
+        if self.uvs.len() != self.positions.len() {
+            return None;
+        }
+        let [a, b, c] = self.indices[face];
+        let (ua, va) = self.uvs[a as usize];
+        let (ub, vb) = self.uvs[b as usize];
+        let (uc, vc) = self.uvs[c as usize];
+        let (du02, dv02) = (ua - uc, va - vc);
+        let (du12, dv12) = (ub - uc, vb - vc);
+        let det = du02 * dv12 - dv02 * du12;
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let (pa, pb, pc) = self.vertices(face);
+        Some(((pa - pc) * dv12 - (pb - pc) * dv02) / det)
+    }
+
+    // Padded so that axis-aligned faces still get a box with some volume.
+    pub fn bounding_box(&self, face: usize) -> AABB {
+        let (a, b, c) = self.vertices(face);
+        let pad = Vec3::new(0.0001, 0.0001, 0.0001);
+        AABB::new(
+            Point3::new(
+                a.x.min(b.x.min(c.x)),
+                a.y.min(b.y.min(c.y)),
+                a.z.min(b.z.min(c.z)),
+            ) - pad,
+            Point3::new(
+                a.x.max(b.x.max(c.x)),
+                a.y.max(b.y.max(c.y)),
+                a.z.max(b.z.max(c.z)),
+            ) + pad,
+        )
+    }
+
+    // Fills in everything the vertex buffers give for a hit found by `intersect`.
+    #[allow(clippy::many_single_char_names)]
+    pub fn hit_record<'a>(
+        &self,
+        face: usize,
+        r: Ray,
+        t: f64,
+        b1: f64,
+        b2: f64,
+        mp: &'a dyn Material,
+    ) -> HitRecord<'a> {
+        let (a, b, c) = self.vertices(face);
+        let n = Vec3::cross(b - a, c - a).to_unit();
+        let (u, v) = self.uv(face, b1, b2).unwrap_or((b1, b2));
+        // Interpolated rather than taken along the ray, it stays on the plane of
+        // the triangle up to rounding
+        let b0 = 1. - b1 - b2;
+        let p = a * b0 + b * b1 + c * b2;
+        let mut rec = HitRecord::new(p, n, t, u, v, true, mp);
+        rec.set_face_normal(r, n);
+        let abs = |v: Vec3| Vec3::new(v.x.abs(), v.y.abs(), v.z.abs());
+        rec.p_error = (abs(a * b0) + abs(b * b1) + abs(c * b2)) * gamma(7);
+        if let Some(color) = self.color(face, b1, b2) {
+            rec.color = color;
+        }
+        if let Some(dpdu) = self.dpdu(face) {
+            rec.dpdu = dpdu;
+        }
+        if let Some(shading_normal) = self.normal(face, b1, b2) {
+            rec.set_shading_normal(r, shading_normal);
+        }
+        rec
+    }
+
+    // Möller-Trumbore in `Float`, returns (t, b1, b2) with the barycentrics of the
+    // second and third vertex.
+    #[allow(clippy::many_single_char_names)]
+    pub fn intersect(
+        &self,
+        face: usize,
+        r: &FloatRay,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<(f64, f64, f64)> {
+        let [ia, ib, ic] = self.indices[face];
+        let a = self.positions[ia as usize];
+        let e1 = self.positions[ib as usize] - a;
+        let e2 = self.positions[ic as usize] - a;
+        let pvec = Vec3f::cross(r.dir, e2);
+        let det = Vec3f::dot(e1, pvec);
+        if det == 0. || !det.is_finite() {
+            return None;
+        }
+        let inv_det = 1. / det;
+        let tvec = r.orig - a;
+        let b1 = Vec3f::dot(tvec, pvec) * inv_det;
+        if !(0. ..=1.).contains(&b1) {
+            return None;
+        }
+        let qvec = Vec3f::cross(tvec, e1);
+        let b2 = Vec3f::dot(r.dir, qvec) * inv_det;
+        if b2 < 0. || b1 + b2 > 1. {
+            return None;
+        }
+        let t = (Vec3f::dot(e2, qvec) * inv_det) as f64;
+        if t < t_min || t > t_max {
+            return None;
+        }
+        Some((t, b1 as f64, b2 as f64))
+    }
+}
+
+// Four faces side by side, one per lane, to test a ray against all of them at once
+// with the same Möller-Trumbore as `MeshData::intersect`.
+struct Triangle4 {
+    v0: [[Float; 4]; 3],
+    e1: [[Float; 4]; 3],
+    e2: [[Float; 4]; 3],
+    faces: [u32; 4],
+    // Bit i is set when lane i holds a face.
+    valid: u32,
+}
+
+impl Triangle4 {
+    fn new(data: &MeshData, faces: &[u32]) -> Self {
+        let mut pack = Self {
+            v0: [[0.; 4]; 3],
+            e1: [[0.; 4]; 3],
+            e2: [[0.; 4]; 3],
+            faces: [0; 4],
+            valid: 0,
+        };
+        for (lane, &face) in faces.iter().enumerate() {
+            if face == PADDING {
+                continue;
+            }
+            let [ia, ib, ic] = data.indices[face as usize];
+            let a = data.positions[ia as usize];
+            let e1 = data.positions[ib as usize] - a;
+            let e2 = data.positions[ic as usize] - a;
+            for axis in 0..3 {
+                pack.v0[axis][lane] = a[axis];
+                pack.e1[axis][lane] = e1[axis];
+                pack.e2[axis][lane] = e2[axis];
+            }
+            pack.faces[lane] = face;
+            pack.valid |= 1 << lane;
+        }
+        pack
+    }
+
+    // The closest hit as (face, t, b1, b2).
+    #[allow(clippy::many_single_char_names)]
+    fn hit(&self, r: &Ray4, t_min: f64, t_max: f64) -> Option<(usize, f64, f64, f64)> {
+        let load = |v: &[[Float; 4]; 3]| [F4::load(&v[0]), F4::load(&v[1]), F4::load(&v[2])];
+        let (e1, e2) = (load(&self.e1), load(&self.e2));
+        let v0 = load(&self.v0);
+        let zero = F4::splat(0.);
+
+        let pvec = cross(&r.dir, &e2);
+        let det = dot(&e1, &pvec);
+        let inv_det = F4::splat(1.) / det;
+        let tvec = [r.orig[0] - v0[0], r.orig[1] - v0[1], r.orig[2] - v0[2]];
+        let b1 = dot(&tvec, &pvec) * inv_det;
+        let qvec = cross(&tvec, &e1);
+        let b2 = dot(&r.dir, &qvec) * inv_det;
+        let t = dot(&e2, &qvec) * inv_det;
+
+        // All false for the NaN a degenerate face gives
+        let mut mask = self.valid
+            & (F4::lt(det, zero) | F4::lt(zero, det))
+            & F4::le(zero, b1)
+            & F4::le(zero, b2)
+            & F4::le(b1 + b2, F4::splat(1.))
+            & F4::le(F4::splat(round_down(t_min)), t)
+            & F4::le(t, F4::splat(round_up(t_max)));
+        if mask == 0 {
+            return None;
+        }
+
+        let (t, b1, b2) = (t.to_array(), b1.to_array(), b2.to_array());
+        let mut closest = None;
+        let mut t_closest = t_max;
+        while mask != 0 {
+            let lane = mask.trailing_zeros() as usize;
+            mask &= mask - 1;
+            let t = t[lane] as f64;
+            if t >= t_min && t <= t_closest {
+                closest = Some((
+                    self.faces[lane] as usize,
+                    t,
+                    b1[lane] as f64,
+                    b2[lane] as f64,
+                ));
+                t_closest = t;
+            }
+        }
+        closest
+    }
+}
+
+// Triangles of a shared `MeshData`, in a 4-wide BVH whose leaves are tested four
+// faces at a time.
+pub struct TriangleMesh<M>
+where
+    M: Material,
+{
+    data: Arc<MeshData>,
+    bvh: Bvh4,
+    // One per four entries of `bvh.order`
+    packs: Vec<Triangle4>,
+    mp: M,
+}
+
+impl<M: Material> TriangleMesh<M> {
+    pub fn new(data: Arc<MeshData>, mp: M) -> Self {
+        let boxes: Vec<AABB> = (0..data.indices.len())
+            .map(|face| data.bounding_box(face))
+            .collect();
+        let bvh = Bvh4::new(&LinearBvh::new(&boxes));
+        let packs = bvh
+            .order
+            .chunks(4)
+            .map(|faces| Triangle4::new(&data, faces))
+            .collect();
+        Self {
+            data,
+            bvh,
+            packs,
+            mp,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn data(&self) -> &Arc<MeshData> {
+        &self.data
+    }
+}
+
+impl<M: Material> Hittable for TriangleMesh<M> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut closest = None;
+        let r4 = Ray4::new(r);
+        self.bvh.hit(&r4, t_min, t_max, |range, mut t_max| {
+            let mut hit = None;
+            for pack in &self.packs[range.start / 4..range.end / 4] {
+                if let Some((face, t, b1, b2)) = pack.hit(&r4, t_min, t_max) {
+                    closest = Some((face, t, b1, b2));
+                    hit = Some(t);
+                    t_max = t;
+                }
+            }
+            hit
+        });
+
+        let (face, t, b1, b2) = closest?;
+        Some(self.data.hit_record(face, r, t, b1, b2, &self.mp))
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        self.bvh.bounds()
+    }
+}
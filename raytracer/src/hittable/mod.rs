@@ -2,8 +2,17 @@ pub mod aarect;
 pub mod boxes;
 pub mod bvh;
 pub mod constantmedium;
+pub mod csg;
+pub mod curve;
+pub mod heightfield;
+pub mod instance;
+pub mod mesh;
+pub mod quad;
+pub mod quadric;
 pub mod ring;
+pub mod sdf;
 pub mod sphere;
+pub mod torus;
 pub mod triangle;
 
 use std::sync::Arc;
@@ -11,13 +20,24 @@ use std::sync::Arc;
 use rand::Rng;
 
 use super::basic::ray::Ray;
-use super::basic::vec::{Point3, Vec3};
+use super::basic::vec::{Color, Point3, Vec3};
 use super::hittable::bvh::aabb::AABB;
 use super::material::Material;
 
+const SHADING_NORMAL_EPS: f64 = 0.01;
+
+#[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
     pub p: Point3,
     pub normal: Vec3,
+    // Normal of the actual surface, on the same side as `normal`.
+    pub geometric_normal: Vec3,
+    // Direction in which `u` grows, zero if the surface has no texture coordinates.
+    pub dpdu: Vec3,
+    // Interpolated vertex color, white if the surface has none.
+    pub color: Color,
+    // Bound on the error of `p` in each axis, for surfaces which know it.
+    pub p_error: Vec3,
     pub t: f64,
     pub u: f64,
     pub v: f64,
@@ -38,6 +58,10 @@ impl<'a> HitRecord<'a> {
         Self {
             p,
             normal,
+            geometric_normal: normal,
+            dpdu: Vec3::new(0., 0., 0.),
+            color: Color::new(1., 1., 1.),
+            p_error: Vec3::new(0., 0., 0.),
             t,
             u,
             v,
@@ -53,6 +77,37 @@ impl<'a> HitRecord<'a> {
         } else {
             -outward_normal
         };
+        self.geometric_normal = self.normal;
+    }
+
+    // A ray leaving the surface, started far enough off it that the error in `p`
+    // cannot put it on the wrong side.
+    pub fn spawn_ray(&self, dir: Vec3, tm: f64) -> Ray {
+        let n = self.geometric_normal;
+        let dis =
+            n.x.abs() * self.p_error.x + n.y.abs() * self.p_error.y + n.z.abs() * self.p_error.z;
+        let offset = if Vec3::dot(dir, n) < 0. {
+            -n * dis
+        } else {
+            n * dis
+        };
+        Ray::new(self.p + offset, dir, tm)
+    }
+
+    // Replaces `normal` by an interpolated one, bent towards the ray if needed so
+    // that it never faces away from the viewer. Call after `set_face_normal`.
+    pub fn set_shading_normal(&mut self, r: Ray, outward_normal: Vec3) {
+        let mut normal = if self.front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+        let wo = -r.dir.to_unit();
+        let cos = Vec3::dot(normal, wo);
+        if cos < SHADING_NORMAL_EPS {
+            normal += wo * (SHADING_NORMAL_EPS - cos);
+        }
+        self.normal = normal.to_unit();
     }
 }
 
@@ -65,6 +120,36 @@ pub trait Hittable: Send + Sync {
     fn random(&self, _o: Point3) -> Vec3 {
         Vec3::new(1., 0., 0.)
     }
+    // Radiance seen by rays which escape the scene in `direction`.
+    fn environment(&self, _direction: Vec3) -> Color {
+        Color::new(0., 0., 0.)
+    }
+    // Total emitted power. Unbounded lights give it per unit area of the scene instead.
+    fn power(&self) -> f64 {
+        0.
+    }
+}
+
+// Lets one object be shared, e.g. as the prototype of many instances.
+impl Hittable for Arc<dyn Hittable> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.as_ref().hit(r, t_min, t_max)
+    }
+    fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
+        self.as_ref().bounding_box(time0, time1)
+    }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.as_ref().pdf_value(o, v)
+    }
+    fn random(&self, o: Point3) -> Vec3 {
+        self.as_ref().random(o)
+    }
+    fn environment(&self, direction: Vec3) -> Color {
+        self.as_ref().environment(direction)
+    }
+    fn power(&self) -> f64 {
+        self.as_ref().power()
+    }
 }
 
 #[derive(Clone)]
@@ -130,6 +215,16 @@ impl Hittable for HittableList {
         let target = rand::thread_rng().gen_range(0..self.objects.len());
         self.objects[target].random(o)
     }
+    fn environment(&self, direction: Vec3) -> Color {
+        let mut sum = Color::new(0., 0., 0.);
+        for object in &self.objects {
+            sum += object.environment(direction);
+        }
+        sum
+    }
+    fn power(&self) -> f64 {
+        self.objects.iter().map(|object| object.power()).sum()
+    }
 }
 
 pub struct Translate<H>
@@ -166,12 +261,23 @@ impl<H: Hittable> Hittable for Translate<H> {
         let moved_r = Ray::new(r.orig - self.offset, r.dir, r.tm);
         if let Some(mut rec) = self.ptr.hit(moved_r, t_min, t_max) {
             rec.p += self.offset;
-            rec.set_face_normal(moved_r, rec.normal);
             Some(rec)
         } else {
             None
         }
     }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.ptr.pdf_value(o - self.offset, v)
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        self.ptr.random(o - self.offset)
+    }
+
+    fn power(&self) -> f64 {
+        self.ptr.power()
+    }
 }
 
 pub struct RotateY<H>
@@ -226,6 +332,22 @@ impl<H: Hittable> RotateY<H> {
             }
         }
     }
+
+    fn to_object(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x - self.sin_theta * v.z,
+            v.y,
+            self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x + self.sin_theta * v.z,
+            v.y,
+            -self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
 }
 
 impl<H: Hittable> Hittable for RotateY<H> {
@@ -245,23 +367,33 @@ impl<H: Hittable> Hittable for RotateY<H> {
         let rotated_r = Ray::new(origin, direction, r.tm);
 
         if let Some(mut rec) = self.ptr.hit(rotated_r, t_min, t_max) {
-            let mut p = rec.p;
-            let mut normal = rec.normal;
-
-            p[0] = self.cos_theta * rec.p[0] + self.sin_theta * rec.p[2];
-            p[2] = -self.sin_theta * rec.p[0] + self.cos_theta * rec.p[2];
-
-            normal[0] = self.cos_theta * rec.normal[0] + self.sin_theta * rec.normal[2];
-            normal[2] = -self.sin_theta * rec.normal[0] + self.cos_theta * rec.normal[2];
-
-            rec.p = p;
-            rec.set_face_normal(rotated_r, normal);
+            rec.p = self.to_world(rec.p);
+            rec.p_error = Vec3::new(
+                self.cos_theta.abs() * rec.p_error.x + self.sin_theta.abs() * rec.p_error.z,
+                rec.p_error.y,
+                self.sin_theta.abs() * rec.p_error.x + self.cos_theta.abs() * rec.p_error.z,
+            );
+            rec.normal = self.to_world(rec.normal);
+            rec.geometric_normal = self.to_world(rec.geometric_normal);
+            rec.dpdu = self.to_world(rec.dpdu);
 
             Some(rec)
         } else {
             None
         }
     }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.ptr.pdf_value(self.to_object(o), self.to_object(v))
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        self.to_world(self.ptr.random(self.to_object(o)))
+    }
+
+    fn power(&self) -> f64 {
+        self.ptr.power()
+    }
 }
 
 pub struct FlipFace<H>
@@ -289,6 +421,15 @@ impl<H: Hittable> Hittable for FlipFace<H> {
     fn bounding_box(&self, time0: f64, time1: f64) -> Option<AABB> {
         self.ptr.bounding_box(time0, time1)
     }
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.ptr.pdf_value(o, v)
+    }
+    fn random(&self, o: Point3) -> Vec3 {
+        self.ptr.random(o)
+    }
+    fn power(&self) -> f64 {
+        self.ptr.power()
+    }
 }
 
 pub struct Zoom<H>
@@ -300,6 +441,7 @@ where
 }
 
 impl<H: Hittable> Zoom<H> {
+    #[allow(dead_code)]
     pub fn new(p: H, rate: f64) -> Self {
         Self { ptr: p, rate }
     }
@@ -319,13 +461,27 @@ impl<H: Hittable> Hittable for Zoom<H> {
     }
 
     fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let moved_r = Ray::new(r.orig / self.rate, r.dir, r.tm);
+        // Scaling the direction as well keeps distances along the ray
+        let moved_r = Ray::new(r.orig / self.rate, r.dir / self.rate, r.tm);
         if let Some(mut rec) = self.ptr.hit(moved_r, t_min, t_max) {
             rec.p *= self.rate;
-            rec.set_face_normal(moved_r, rec.normal);
+            rec.dpdu *= self.rate;
             Some(rec)
         } else {
             None
         }
     }
+
+    // Directions and solid angles are unchanged by a uniform scale.
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        self.ptr.pdf_value(o / self.rate, v)
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        self.ptr.random(o / self.rate)
+    }
+
+    fn power(&self) -> f64 {
+        self.ptr.power() * self.rate * self.rate
+    }
 }
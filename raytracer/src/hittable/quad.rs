@@ -0,0 +1,115 @@
+use std::f64::INFINITY;
+
+use rand::Rng;
+
+use crate::{
+    basic::ray::Ray,
+    basic::vec::{Point3, Vec3},
+    hittable::bvh::aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+};
+
+// The parallelogram with corners q, q + u, q + v and q + u + v.
+#[derive(Clone)]
+pub struct Quad<M>
+where
+    M: Material,
+{
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    // Maps a point of the plane, relative to q, to its coordinates along u and v.
+    w: Vec3,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+    mp: M,
+}
+
+impl<M: Material> Quad<M> {
+    // Faces towards u x v.
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mp: M) -> Self {
+        let n = Vec3::cross(u, v);
+        let normal = n.to_unit();
+        Self {
+            q,
+            u,
+            v,
+            w: n / n.length_sqr(),
+            normal,
+            d: Vec3::dot(normal, q),
+            area: n.length(),
+            mp,
+        }
+    }
+
+    // Faces the other way, keeping the texture coordinates.
+    pub fn flipped(mut self) -> Self {
+        self.normal = -self.normal;
+        self.d = -self.d;
+        self
+    }
+}
+
+impl<M: Material> Hittable for Quad<M> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let denom = Vec3::dot(self.normal, r.dir);
+        if denom == 0. {
+            return None;
+        }
+        let t = (self.d - Vec3::dot(self.normal, r.orig)) / denom;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = Vec3::dot(self.w, Vec3::cross(planar, self.v));
+        let beta = Vec3::dot(self.w, Vec3::cross(self.u, planar));
+        if !(0. ..=1.).contains(&alpha) || !(0. ..=1.).contains(&beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord::new(p, self.normal, t, alpha, beta, true, &self.mp);
+        rec.set_face_normal(r, self.normal);
+        rec.dpdu = self.u;
+
+        Some(rec)
+    }
+
+    // Padded so that flat quads still have a volume.
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        let thickness = 0.0001;
+        let mut min = self.q;
+        let mut max = self.q;
+        for corner in [self.q + self.u, self.q + self.v, self.q + self.u + self.v].iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(corner[i]);
+                max[i] = max[i].max(corner[i]);
+            }
+        }
+        let padding = Vec3::new(thickness, thickness, thickness);
+        Some(AABB::new(min - padding, max + padding))
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        if let Some(rec) = self.hit(Ray::new(o, v, 0.), 0.001, INFINITY) {
+            let dis_sqr = rec.t * rec.t * v.length_sqr();
+            let cos = (Vec3::dot(v, rec.normal) / v.length()).abs();
+            dis_sqr / (cos * self.area)
+        } else {
+            0.
+        }
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let random_point = self.q + self.u * rng.gen::<f64>() + self.v * rng.gen::<f64>();
+        random_point - origin
+    }
+
+    fn power(&self) -> f64 {
+        self.area * self.mp.radiant_exitance()
+    }
+}
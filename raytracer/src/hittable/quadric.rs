@@ -0,0 +1,574 @@
+use std::f64::consts::PI;
+use std::f64::INFINITY;
+
+use rand::Rng;
+
+use crate::{
+    basic::{
+        onb::Onb,
+        poly::solve_quadratic,
+        ray::Ray,
+        vec::{Point3, Vec3},
+    },
+    material::Material,
+};
+
+use super::{bvh::aabb::AABB, HitRecord, Hittable};
+
+// Local coordinate system of a shape, with `origin` at zero and `axis` along z.
+#[derive(Clone, Copy)]
+pub struct Frame {
+    origin: Point3,
+    uvw: Onb,
+}
+
+// A hit in the local coordinates of a shape.
+#[derive(Clone, Copy)]
+pub struct LocalHit {
+    pub t: f64,
+    pub normal: Vec3,
+    pub u: f64,
+    pub v: f64,
+    pub dpdu: Vec3,
+}
+
+impl Frame {
+    pub fn new(origin: Point3, axis: Vec3) -> Self {
+        Self {
+            origin,
+            uvw: Onb::build_from_w(axis),
+        }
+    }
+
+    pub fn axis(&self) -> Vec3 {
+        self.uvw.w()
+    }
+
+    pub fn point_to_world(&self, p: Point3) -> Point3 {
+        self.origin + self.uvw.local_vec(p)
+    }
+
+    pub fn point_to_local(&self, p: Point3) -> Point3 {
+        self.vector_to_local(p - self.origin)
+    }
+
+    pub fn vector_to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(v, self.uvw.u()),
+            Vec3::dot(v, self.uvw.v()),
+            Vec3::dot(v, self.uvw.w()),
+        )
+    }
+
+    // The axes are orthonormal, so `t` is the same in both spaces.
+    pub fn ray_to_local(&self, r: Ray) -> (Point3, Vec3) {
+        (self.point_to_local(r.orig), self.vector_to_local(r.dir))
+    }
+
+    pub fn record<'a>(&self, r: Ray, hit: LocalHit, mat: &'a dyn Material) -> HitRecord<'a> {
+        let outward_normal = self.uvw.local_vec(hit.normal).to_unit();
+        let mut rec = HitRecord::new(r.at(hit.t), outward_normal, hit.t, hit.u, hit.v, false, mat);
+        rec.set_face_normal(r, outward_normal);
+        rec.dpdu = self.uvw.local_vec(hit.dpdu);
+        rec
+    }
+
+    // Bounds of the circle of the given radius around the axis at height `z`.
+    pub fn circle_box(&self, z: f64, radius: f64) -> AABB {
+        let thickness = 0.0001;
+        let center = self.point_to_world(Point3::new(0., 0., z));
+        let w = self.axis();
+        let extent = Vec3::new(
+            radius * (1. - w.x * w.x).max(0.).sqrt() + thickness,
+            radius * (1. - w.y * w.y).max(0.).sqrt() + thickness,
+            radius * (1. - w.z * w.z).max(0.).sqrt() + thickness,
+        );
+        AABB::new(center - extent, center + extent)
+    }
+}
+
+// Angle around the local z axis, in [0, 2pi).
+pub fn azimuth(p: Point3) -> f64 {
+    let phi = p.y.atan2(p.x);
+    if phi < 0. {
+        phi + 2. * PI
+    } else {
+        phi
+    }
+}
+
+// Density of sampling `shape` uniformly by area, per solid angle seen from `o`.
+// Every point of the shape along `v` could have been the one sampled.
+pub fn area_pdf<H: Hittable>(shape: &H, area: f64, o: Point3, v: Vec3) -> f64 {
+    let mut pdf = 0.;
+    let mut t_min = 0.001;
+    while let Some(rec) = shape.hit(Ray::new(o, v, 0.), t_min, INFINITY) {
+        let dis_sqr = rec.t * rec.t * v.length_sqr();
+        let cos = (Vec3::dot(v, rec.geometric_normal) / v.length()).abs();
+        pdf += dis_sqr / (cos * area);
+        t_min = rec.t + 0.001;
+    }
+    pdf
+}
+
+// Hit with the annulus at height `z` between `inner` and `outer`, facing +z.
+#[allow(clippy::many_single_char_names)]
+fn disk_hit(
+    o: Point3,
+    d: Vec3,
+    z: f64,
+    inner: f64,
+    outer: f64,
+    t_min: f64,
+    t_max: f64,
+) -> Option<LocalHit> {
+    let t = (z - o.z) / d.z;
+    if t.is_nan() || t < t_min || t > t_max {
+        return None;
+    }
+    let p = o + d * t;
+    let dis = (p.x * p.x + p.y * p.y).sqrt();
+    if dis < inner || dis > outer {
+        return None;
+    }
+    Some(LocalHit {
+        t,
+        normal: Vec3::new(0., 0., 1.),
+        u: azimuth(p) / (2. * PI),
+        v: (outer - dis) / (outer - inner),
+        dpdu: Vec3::new(-p.y, p.x, 0.) * (2. * PI),
+    })
+}
+
+// First root of a x^2 + b x + c in [t_min, t_max] at which the ray is at a height
+// in [0, height].
+#[allow(clippy::many_single_char_names, clippy::too_many_arguments)]
+fn side_root(
+    a: f64,
+    b: f64,
+    c: f64,
+    o: Point3,
+    d: Vec3,
+    height: f64,
+    t_min: f64,
+    t_max: f64,
+) -> Option<f64> {
+    let (t0, t1) = solve_quadratic(a, b, c)?;
+    [t0, t1].iter().copied().find(|&t| {
+        let z = o.z + t * d.z;
+        t >= t_min && t <= t_max && (0. ..=height).contains(&z)
+    })
+}
+
+pub struct Cylinder<M>
+where
+    M: Material,
+{
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mat: M,
+}
+
+impl<M: Material> Cylinder<M> {
+    // Open tube around the segment from `base` to `top`.
+    pub fn new(base: Point3, top: Point3, radius: f64, mat: M) -> Self {
+        Self {
+            frame: Frame::new(base, top - base),
+            radius,
+            height: (top - base).length(),
+            capped: false,
+            mat,
+        }
+    }
+
+    // Closes both ends with disks.
+    pub fn capped(mut self) -> Self {
+        self.capped = true;
+        self
+    }
+
+    fn side_area(&self) -> f64 {
+        2. * PI * self.radius * self.height
+    }
+
+    fn area(&self) -> f64 {
+        if self.capped {
+            self.side_area() + 2. * PI * self.radius * self.radius
+        } else {
+            self.side_area()
+        }
+    }
+}
+
+impl<M: Material> Hittable for Cylinder<M> {
+    #[allow(clippy::many_single_char_names)]
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        let a = d.x * d.x + d.y * d.y;
+        let b = 2. * (o.x * d.x + o.y * d.y);
+        let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+
+        let mut hit = side_root(a, b, c, o, d, self.height, t_min, t_max).map(|t| {
+            let p = o + d * t;
+            LocalHit {
+                t,
+                normal: Vec3::new(p.x, p.y, 0.) / self.radius,
+                u: azimuth(p) / (2. * PI),
+                v: p.z / self.height,
+                dpdu: Vec3::new(-p.y, p.x, 0.) * (2. * PI),
+            }
+        });
+        if self.capped {
+            for &z in &[0., self.height] {
+                let t_max = hit.map_or(t_max, |h| h.t);
+                if let Some(mut cap) = disk_hit(o, d, z, 0., self.radius, t_min, t_max) {
+                    if z == 0. {
+                        cap.normal = -cap.normal;
+                    }
+                    hit = Some(cap);
+                }
+            }
+        }
+        hit.map(|h| self.frame.record(r, h, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        Some(AABB::surrounding_box(
+            self.frame.circle_box(0., self.radius),
+            self.frame.circle_box(self.height, self.radius),
+        ))
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        area_pdf(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let phi = rng.gen_range(0.0..2. * PI);
+        let choice = rng.gen_range(0.0..self.area());
+        let p = if choice < self.side_area() {
+            Point3::new(
+                self.radius * phi.cos(),
+                self.radius * phi.sin(),
+                rng.gen_range(0.0..self.height),
+            )
+        } else {
+            let dis = self.radius * rng.gen::<f64>().sqrt();
+            let z = if choice < self.side_area() + PI * self.radius * self.radius {
+                0.
+            } else {
+                self.height
+            };
+            Point3::new(dis * phi.cos(), dis * phi.sin(), z)
+        };
+        self.frame.point_to_world(p) - o
+    }
+
+    fn power(&self) -> f64 {
+        self.area() * self.mat.radiant_exitance()
+    }
+}
+
+pub struct Cone<M>
+where
+    M: Material,
+{
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mat: M,
+}
+
+impl<M: Material> Cone<M> {
+    // Open cone with a base of `radius` around `base`, narrowing to `apex`.
+    pub fn new(base: Point3, apex: Point3, radius: f64, mat: M) -> Self {
+        Self {
+            frame: Frame::new(base, apex - base),
+            radius,
+            height: (apex - base).length(),
+            capped: false,
+            mat,
+        }
+    }
+
+    // Closes the base with a disk.
+    pub fn capped(mut self) -> Self {
+        self.capped = true;
+        self
+    }
+
+    fn side_area(&self) -> f64 {
+        PI * self.radius * self.radius.hypot(self.height)
+    }
+
+    fn area(&self) -> f64 {
+        if self.capped {
+            self.side_area() + PI * self.radius * self.radius
+        } else {
+            self.side_area()
+        }
+    }
+}
+
+impl<M: Material> Hittable for Cone<M> {
+    #[allow(clippy::many_single_char_names)]
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        // x^2 + y^2 = (k (height - z))^2
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let dz = self.height - o.z;
+        let a = d.x * d.x + d.y * d.y - k2 * d.z * d.z;
+        let b = 2. * (o.x * d.x + o.y * d.y + k2 * dz * d.z);
+        let c = o.x * o.x + o.y * o.y - k2 * dz * dz;
+
+        let mut hit = side_root(a, b, c, o, d, self.height, t_min, t_max).map(|t| {
+            let p = o + d * t;
+            let phi = azimuth(p);
+            LocalHit {
+                t,
+                normal: Vec3::new(phi.cos(), phi.sin(), k),
+                u: phi / (2. * PI),
+                v: p.z / self.height,
+                dpdu: Vec3::new(-p.y, p.x, 0.) * (2. * PI),
+            }
+        });
+        if self.capped {
+            let t_max = hit.map_or(t_max, |h| h.t);
+            if let Some(mut cap) = disk_hit(o, d, 0., 0., self.radius, t_min, t_max) {
+                cap.normal = -cap.normal;
+                hit = Some(cap);
+            }
+        }
+        hit.map(|h| self.frame.record(r, h, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        let apex = self.frame.point_to_world(Point3::new(0., 0., self.height));
+        Some(AABB::surrounding_box(
+            self.frame.circle_box(0., self.radius),
+            AABB::new(apex, apex),
+        ))
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        area_pdf(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let phi = rng.gen_range(0.0..2. * PI);
+        let p = if rng.gen_range(0.0..self.area()) < self.side_area() {
+            // The circumference grows linearly away from the apex
+            let s = rng.gen::<f64>().sqrt();
+            let dis = self.radius * s;
+            Point3::new(dis * phi.cos(), dis * phi.sin(), self.height * (1. - s))
+        } else {
+            let dis = self.radius * rng.gen::<f64>().sqrt();
+            Point3::new(dis * phi.cos(), dis * phi.sin(), 0.)
+        };
+        self.frame.point_to_world(p) - o
+    }
+
+    fn power(&self) -> f64 {
+        self.area() * self.mat.radiant_exitance()
+    }
+}
+
+// A disk, or an annulus if it has a hole in the middle. Unlike `Ring`, it can be
+// placed and oriented freely.
+pub struct Disk<M>
+where
+    M: Material,
+{
+    frame: Frame,
+    inner: f64,
+    outer: f64,
+    mat: M,
+}
+
+impl<M: Material> Disk<M> {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, mat: M) -> Self {
+        Self::annulus(center, normal, 0., radius, mat)
+    }
+
+    pub fn annulus(center: Point3, normal: Vec3, inner: f64, outer: f64, mat: M) -> Self {
+        Self {
+            frame: Frame::new(center, normal),
+            inner,
+            outer,
+            mat,
+        }
+    }
+
+    fn area(&self) -> f64 {
+        PI * (self.outer * self.outer - self.inner * self.inner)
+    }
+}
+
+impl<M: Material> Hittable for Disk<M> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        disk_hit(o, d, 0., self.inner, self.outer, t_min, t_max)
+            .map(|h| self.frame.record(r, h, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        Some(self.frame.circle_box(0., self.outer))
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        area_pdf(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let dis = if self.inner < self.outer {
+            rng.gen_range(self.inner * self.inner..self.outer * self.outer)
+                .sqrt()
+        } else {
+            self.outer
+        };
+        let phi = rng.gen_range(0.0..2. * PI);
+        self.frame
+            .point_to_world(Point3::new(dis * phi.cos(), dis * phi.sin(), 0.))
+            - o
+    }
+
+    fn power(&self) -> f64 {
+        self.area() * self.mat.radiant_exitance()
+    }
+}
+
+// An infinite plane through `point`. It has no bounding box, so a BVH tests every
+// ray against it. `u` and `v` are the coordinates along two axes in the plane.
+pub struct Plane<M>
+where
+    M: Material,
+{
+    frame: Frame,
+    mat: M,
+}
+
+impl<M: Material> Plane<M> {
+    pub fn new(point: Point3, normal: Vec3, mat: M) -> Self {
+        Self {
+            frame: Frame::new(point, normal),
+            mat,
+        }
+    }
+}
+
+impl<M: Material> Hittable for Plane<M> {
+    #[allow(clippy::many_single_char_names)]
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        let t = -o.z / d.z;
+        if !t.is_finite() || t < t_min || t > t_max {
+            return None;
+        }
+        let p = o + d * t;
+        let hit = LocalHit {
+            t,
+            normal: Vec3::new(0., 0., 1.),
+            u: p.x,
+            v: p.y,
+            dpdu: Vec3::new(1., 0., 0.),
+        };
+        Some(self.frame.record(r, hit, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        None
+    }
+}
+
+// The bowl z = k (x^2 + y^2) from its vertex up to the rim.
+pub struct Paraboloid<M>
+where
+    M: Material,
+{
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    mat: M,
+}
+
+impl<M: Material> Paraboloid<M> {
+    // Opens from `vertex` towards `rim_center`, where it is `radius` wide.
+    pub fn new(vertex: Point3, rim_center: Point3, radius: f64, mat: M) -> Self {
+        Self {
+            frame: Frame::new(vertex, rim_center - vertex),
+            radius,
+            height: (rim_center - vertex).length(),
+            mat,
+        }
+    }
+
+    fn k(&self) -> f64 {
+        self.height / (self.radius * self.radius)
+    }
+
+    fn area(&self) -> f64 {
+        let k = self.k();
+        let s = 1. + 4. * k * k * self.radius * self.radius;
+        PI * (s.powf(1.5) - 1.) / (6. * k * k)
+    }
+}
+
+impl<M: Material> Hittable for Paraboloid<M> {
+    #[allow(clippy::many_single_char_names)]
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        let k = self.k();
+        let a = k * (d.x * d.x + d.y * d.y);
+        let b = 2. * k * (o.x * d.x + o.y * d.y) - d.z;
+        let c = k * (o.x * o.x + o.y * o.y) - o.z;
+
+        side_root(a, b, c, o, d, self.height, t_min, t_max).map(|t| {
+            let p = o + d * t;
+            let hit = LocalHit {
+                t,
+                normal: Vec3::new(2. * k * p.x, 2. * k * p.y, -1.),
+                u: azimuth(p) / (2. * PI),
+                v: p.z / self.height,
+                dpdu: Vec3::new(-p.y, p.x, 0.) * (2. * PI),
+            };
+            self.frame.record(r, hit, &self.mat)
+        })
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        Some(AABB::surrounding_box(
+            self.frame.circle_box(0., self.radius),
+            self.frame.circle_box(self.height, self.radius),
+        ))
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        area_pdf(self, self.area(), o, v)
+    }
+
+    #[allow(clippy::many_single_char_names)]
+    fn random(&self, o: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        // Inverts the area below distance `dis` from the axis, which is
+        // proportional to (1 + 4 k^2 dis^2)^1.5 - 1
+        let k = self.k();
+        let s = 1. + 4. * k * k * self.radius * self.radius;
+        let x: f64 = rng.gen();
+        let dis_sqr = ((1. + x * (s.powf(1.5) - 1.)).powf(2. / 3.) - 1.) / (4. * k * k);
+        let dis = dis_sqr.max(0.).sqrt();
+        let phi = rng.gen_range(0.0..2. * PI);
+        let p = Point3::new(dis * phi.cos(), dis * phi.sin(), k * dis_sqr);
+        self.frame.point_to_world(p) - o
+    }
+
+    fn power(&self) -> f64 {
+        self.area() * self.mat.radiant_exitance()
+    }
+}
@@ -1,3 +1,8 @@
+use std::f64::consts::PI;
+use std::f64::INFINITY;
+
+use rand::Rng;
+
 use crate::{
     basic::{
         ray::Ray,
@@ -60,4 +65,26 @@ impl<M: Material> Hittable for Ring<M> {
             Point3::new(r, thickness, r),
         ))
     }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        if let Some(rec) = self.hit(Ray::new(o, v, 0.), 0.001, INFINITY) {
+            let area = PI * (self.dis_max - self.dis_min);
+            let dis_sqr = rec.t * rec.t * v.length_sqr();
+            let cos = (Vec3::dot(v, rec.normal) / v.length()).abs();
+            dis_sqr / (cos * area)
+        } else {
+            0.
+        }
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let radius = rng.gen_range(self.dis_min..self.dis_max).sqrt();
+        let phi = rng.gen_range(0.0..2. * PI);
+        Point3::new(radius * phi.cos(), 0., radius * phi.sin()) - o
+    }
+
+    fn power(&self) -> f64 {
+        PI * (self.dis_max - self.dis_min) * self.mat.radiant_exitance()
+    }
 }
@@ -0,0 +1,298 @@
+use crate::{
+    basic::ray::Ray,
+    basic::vec::{Point3, Vec3},
+    hittable::bvh::aabb::AABB,
+    hittable::{HitRecord, Hittable},
+    material::Material,
+};
+
+// A signed distance function: negative inside, positive outside. It may
+// underestimate the distance to the surface but should never overestimate it.
+pub trait Sdf: Send + Sync {
+    fn distance(&self, p: Point3) -> f64;
+}
+
+pub struct SdfSphere {
+    center: Point3,
+    radius: f64,
+}
+
+impl SdfSphere {
+    pub fn new(center: Point3, radius: f64) -> Self {
+        Self { center, radius }
+    }
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: Point3) -> f64 {
+        (p - self.center).length() - self.radius
+    }
+}
+
+// A box of the given half size with its edges rounded by `rounding`.
+pub struct SdfBox {
+    center: Point3,
+    half_size: Vec3,
+    rounding: f64,
+}
+
+impl SdfBox {
+    pub fn new(center: Point3, half_size: Vec3, rounding: f64) -> Self {
+        Self {
+            center,
+            half_size,
+            rounding,
+        }
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: Point3) -> f64 {
+        let p = p - self.center;
+        let q = Vec3::new(
+            p.x.abs() - self.half_size.x + self.rounding,
+            p.y.abs() - self.half_size.y + self.rounding,
+            p.z.abs() - self.half_size.z + self.rounding,
+        );
+        let outside = Vec3::new(q.x.max(0.), q.y.max(0.), q.z.max(0.)).length();
+        let inside = q.x.max(q.y).max(q.z).min(0.);
+        outside + inside - self.rounding
+    }
+}
+
+// A torus lying in the xz plane.
+pub struct SdfTorus {
+    center: Point3,
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+impl SdfTorus {
+    pub fn new(center: Point3, major_radius: f64, minor_radius: f64) -> Self {
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+        }
+    }
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: Point3) -> f64 {
+        let p = p - self.center;
+        let ring = p.x.hypot(p.z) - self.major_radius;
+        ring.hypot(p.y) - self.minor_radius
+    }
+}
+
+// The power `power` Mandelbulb, about 1.2 wide around the origin.
+pub struct Mandelbulb {
+    power: f64,
+    iterations: u32,
+}
+
+impl Mandelbulb {
+    pub fn new(power: f64, iterations: u32) -> Self {
+        Self { power, iterations }
+    }
+}
+
+impl Sdf for Mandelbulb {
+    fn distance(&self, p: Point3) -> f64 {
+        let mut z = p;
+        let mut dr = 1.;
+        let mut r = z.length();
+        for _ in 0..self.iterations {
+            if r > 2. {
+                break;
+            }
+            let theta = (z.z / r).acos() * self.power;
+            let phi = z.y.atan2(z.x) * self.power;
+            dr = r.powf(self.power - 1.) * self.power * dr + 1.;
+            let zr = r.powf(self.power);
+            z = Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            ) * zr
+                + p;
+            r = z.length();
+        }
+        0.5 * r.ln() * r / dr
+    }
+}
+
+// Blends two shapes, rounding the seam over a width of about `k`.
+pub struct SmoothUnion<A, B>
+where
+    A: Sdf,
+    B: Sdf,
+{
+    a: A,
+    b: B,
+    k: f64,
+}
+
+impl<A: Sdf, B: Sdf> SmoothUnion<A, B> {
+    pub fn new(a: A, b: B, k: f64) -> Self {
+        Self { a, b, k }
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SmoothUnion<A, B> {
+    fn distance(&self, p: Point3) -> f64 {
+        let (d1, d2) = (self.a.distance(p), self.b.distance(p));
+        let h = (0.5 + 0.5 * (d2 - d1) / self.k).clamp(0., 1.);
+        d2 + (d1 - d2) * h - self.k * h * (1. - h)
+    }
+}
+
+// Rotates each slice of the shape around the y axis by `rate` radians per unit of
+// height.
+pub struct Twist<S>
+where
+    S: Sdf,
+{
+    inner: S,
+    rate: f64,
+}
+
+impl<S: Sdf> Twist<S> {
+    pub fn new(inner: S, rate: f64) -> Self {
+        Self { inner, rate }
+    }
+}
+
+impl<S: Sdf> Sdf for Twist<S> {
+    fn distance(&self, p: Point3) -> f64 {
+        let angle = self.rate * p.y;
+        let (sin, cos) = angle.sin_cos();
+        let q = Point3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z);
+        // Twisting stretches space by up to this much at this distance from the axis
+        let stretch = (1. + (self.rate * p.x.hypot(p.z)).powi(2)).sqrt();
+        self.inner.distance(q) / stretch
+    }
+}
+
+// Copies the shape around the origin every `period` along each axis. A zero
+// period leaves that axis alone.
+pub struct Repeat<S>
+where
+    S: Sdf,
+{
+    inner: S,
+    period: Vec3,
+}
+
+impl<S: Sdf> Repeat<S> {
+    pub fn new(inner: S, period: Vec3) -> Self {
+        Self { inner, period }
+    }
+}
+
+impl<S: Sdf> Sdf for Repeat<S> {
+    fn distance(&self, p: Point3) -> f64 {
+        let mut q = p;
+        for i in 0..3 {
+            if self.period[i] > 0. {
+                q[i] -= self.period[i] * (p[i] / self.period[i]).round();
+            }
+        }
+        self.inner.distance(q)
+    }
+}
+
+// Renders a distance function by sphere tracing inside `aabbox`, which has to
+// contain the whole surface.
+pub struct SdfObject<S, M>
+where
+    S: Sdf,
+    M: Material,
+{
+    sdf: S,
+    aabbox: AABB,
+    epsilon: f64,
+    max_steps: u32,
+    mp: M,
+}
+
+impl<S: Sdf, M: Material> SdfObject<S, M> {
+    pub fn new(sdf: S, aabbox: AABB, mp: M) -> Self {
+        Self {
+            sdf,
+            aabbox,
+            epsilon: 1e-4,
+            max_steps: 256,
+            mp,
+        }
+    }
+
+    // Distance at which a point counts as on the surface.
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+
+    // Rays which get no closer than `epsilon` in this many steps miss.
+    pub fn max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    // Gradient of the distance by central differences on a tetrahedron.
+    fn gradient(&self, p: Point3) -> Vec3 {
+        let h = self.epsilon;
+        let mut gradient = Vec3::new(0., 0., 0.);
+        for k in [
+            Vec3::new(1., -1., -1.),
+            Vec3::new(-1., -1., 1.),
+            Vec3::new(-1., 1., -1.),
+            Vec3::new(1., 1., 1.),
+        ]
+        .iter()
+        {
+            gradient += *k * self.sdf.distance(p + *k * h);
+        }
+        gradient
+    }
+}
+
+impl<S: Sdf, M: Material> Hittable for SdfObject<S, M> {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_start, t_end) = self.aabbox.clip(r, t_min, t_max)?;
+        let len = r.dir.length();
+
+        // Marches on the side the ray starts from. A ray leaving the surface it
+        // starts on counts as coming from the side it heads to.
+        let mut t = t_start;
+        let start = self.sdf.distance(r.at(t));
+        let side = if start.abs() < self.epsilon {
+            Vec3::dot(self.gradient(r.at(t)), r.dir).signum()
+        } else {
+            start.signum()
+        };
+        let mut leaving = side * start < self.epsilon;
+
+        for _ in 0..self.max_steps {
+            if t > t_end {
+                return None;
+            }
+            let distance = side * self.sdf.distance(r.at(t));
+            if leaving {
+                leaving = distance < self.epsilon;
+            } else if distance < self.epsilon {
+                let p = r.at(t);
+                let outward_normal = self.gradient(p).to_unit();
+                let mut rec = HitRecord::new(p, outward_normal, t, 0., 0., false, &self.mp);
+                rec.set_face_normal(r, outward_normal);
+                return Some(rec);
+            }
+            t += distance.max(self.epsilon) / len;
+        }
+        None
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        Some(self.aabbox)
+    }
+}
@@ -88,6 +88,10 @@ impl<M: Material + Clone> Hittable for Sphere<M> {
         let uvw = Onb::build_from_w(direction);
         uvw.local_vec(random_to_sphere(self.radius, dis_sqr))
     }
+
+    fn power(&self) -> f64 {
+        4. * PI * self.radius * self.radius * self.mat_ptr.radiant_exitance()
+    }
 }
 
 pub struct MovingSphere<M>
@@ -175,4 +179,8 @@ impl<M: Material> Hittable for MovingSphere<M> {
         );
         Some(AABB::surrounding_box(box0, box1))
     }
+
+    fn power(&self) -> f64 {
+        4. * PI * self.radius * self.radius * self.mat_ptr.radiant_exitance()
+    }
 }
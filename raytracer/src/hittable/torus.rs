@@ -0,0 +1,126 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::{
+    basic::{
+        poly::solve_quartic,
+        ray::Ray,
+        vec::{Point3, Vec3},
+    },
+    material::Material,
+};
+
+use super::{
+    bvh::aabb::AABB,
+    quadric::{area_pdf, azimuth, Frame, LocalHit},
+    HitRecord, Hittable,
+};
+
+pub struct Torus<M>
+where
+    M: Material,
+{
+    frame: Frame,
+    major_radius: f64,
+    minor_radius: f64,
+    mat: M,
+}
+
+impl<M: Material> Torus<M> {
+    // A tube of `minor_radius` around the circle of `major_radius` about `axis`.
+    pub fn new(center: Point3, axis: Vec3, major_radius: f64, minor_radius: f64, mat: M) -> Self {
+        Self {
+            frame: Frame::new(center, axis),
+            major_radius,
+            minor_radius,
+            mat,
+        }
+    }
+
+    fn area(&self) -> f64 {
+        4. * PI * PI * self.major_radius * self.minor_radius
+    }
+}
+
+impl<M: Material> Hittable for Torus<M> {
+    #[allow(clippy::many_single_char_names)]
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (o, d) = self.frame.ray_to_local(r);
+        let (big_r, small_r) = (self.major_radius, self.minor_radius);
+
+        // Solve from the point of the ray closest to the center with a unit
+        // direction, which keeps the coefficients small for distant rays
+        let len = d.length();
+        let d = d / len;
+        let t_center = -Vec3::dot(o, d);
+        let o = o + d * t_center;
+        let extent = (big_r + small_r) * (1. + 1e-9);
+        let lo = (t_min * len - t_center).max(-extent);
+        let hi = (t_max * len - t_center).min(extent);
+        if lo > hi {
+            return None;
+        }
+
+        // (|p|^2 + R^2 - r^2)^2 = 4 R^2 (x^2 + y^2)
+        let e = o.length_sqr() + big_r * big_r - small_r * small_r;
+        let f = Vec3::dot(o, d);
+        let four_r2 = 4. * big_r * big_r;
+        let coefficients = [
+            e * e - four_r2 * (o.x * o.x + o.y * o.y),
+            4. * f * e - 2. * four_r2 * (o.x * d.x + o.y * d.y),
+            4. * f * f + 2. * e - four_r2 * (d.x * d.x + d.y * d.y),
+            4. * f,
+            1.,
+        ];
+        let s = *solve_quartic(coefficients, lo, hi).first()?;
+
+        let p = o + d * s;
+        let phi = azimuth(p);
+        let ring = Vec3::new(phi.cos(), phi.sin(), 0.) * big_r;
+        let theta = p.z.atan2(p.x.hypot(p.y) - big_r);
+        let hit = LocalHit {
+            t: (t_center + s) / len,
+            normal: p - ring,
+            u: phi / (2. * PI),
+            v: if theta < 0. { theta + 2. * PI } else { theta } / (2. * PI),
+            dpdu: Vec3::new(-p.y, p.x, 0.) * (2. * PI),
+        };
+        Some(self.frame.record(r, hit, &self.mat))
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        let ring = self.frame.circle_box(0., self.major_radius);
+        let tube = Vec3::new(self.minor_radius, self.minor_radius, self.minor_radius);
+        Some(AABB::new(ring.min - tube, ring.max + tube))
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        area_pdf(self, self.area(), o, v)
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        // The outside of the tube is larger than the inside
+        let theta = loop {
+            let theta = rng.gen_range(0.0..2. * PI);
+            let accept = (self.major_radius + self.minor_radius * theta.cos())
+                / (self.major_radius + self.minor_radius);
+            if rng.gen::<f64>() < accept {
+                break theta;
+            }
+        };
+        let phi = rng.gen_range(0.0..2. * PI);
+        let dis = self.major_radius + self.minor_radius * theta.cos();
+        let p = Point3::new(
+            dis * phi.cos(),
+            dis * phi.sin(),
+            self.minor_radius * theta.sin(),
+        );
+        self.frame.point_to_world(p) - o
+    }
+
+    fn power(&self) -> f64 {
+        self.area() * self.mat.radiant_exitance()
+    }
+}
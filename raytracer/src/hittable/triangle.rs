@@ -1,3 +1,8 @@
+use std::f64::consts::PI;
+use std::f64::INFINITY;
+
+use rand::Rng;
+
 use crate::{
     basic::{
         ray::Ray,
@@ -8,6 +13,7 @@ use crate::{
 
 use super::{bvh::aabb::AABB, HitRecord, Hittable};
 
+// Edges, normal and area are kept from `new`, so a hit costs two cross products.
 pub struct Triangle<M>
 where
     M: Material,
@@ -15,62 +21,62 @@ where
     a: Point3,
     b: Point3,
     c: Point3,
+    e1: Vec3,
+    e2: Vec3,
+    normal: Vec3,
+    area: f64,
     mp: M,
 }
 
 impl<M: Material> Triangle<M> {
     pub fn new(x: Point3, y: Point3, z: Point3, mp: M) -> Self {
+        let (e1, e2) = (y - x, z - x);
+        let n = Vec3::cross(e1, e2);
         Self {
             a: x,
             b: y,
             c: z,
+            e1,
+            e2,
+            normal: n.to_unit(),
+            area: n.length() / 2.,
             mp,
         }
     }
     pub fn get_normal(&self) -> Vec3 {
-        Vec3::cross(self.b - self.a, self.c - self.a).to_unit()
+        self.normal
     }
-    pub fn inside(&self, p: Point3) -> bool {
-        Vec3::dot(
-            Vec3::cross(self.c - self.a, p - self.a),
-            Vec3::cross(self.c - self.a, self.b - self.a),
-        ) >= 0.
-            && Vec3::dot(
-                Vec3::cross(self.a - self.b, p - self.b),
-                Vec3::cross(self.a - self.b, self.c - self.b),
-            ) >= 0.
-            && Vec3::dot(
-                Vec3::cross(self.b - self.c, p - self.c),
-                Vec3::cross(self.b - self.c, self.a - self.c),
-            ) >= 0.
+    pub fn area(&self) -> f64 {
+        self.area
     }
 }
 
 impl<M: Material> Hittable for Triangle<M> {
-    #[allow(clippy::many_single_char_names)]
+    // Möller-Trumbore, `u` and `v` are the barycentrics of `b` and `c`.
     fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let origin = r.orig;
-        let direction = r.dir;
-        let n = self.get_normal();
-        let t = Vec3::dot(direction, n);
-        let t = Vec3::dot(self.a - origin, n) / t;
-        if t.is_nan() || t < t_min || t > t_max {
+        let pvec = Vec3::cross(r.dir, self.e2);
+        let det = Vec3::dot(self.e1, pvec);
+        if det == 0. || !det.is_finite() {
             return None;
         }
-        let p = origin + direction * t;
-        if !self.inside(p) {
+        let inv_det = 1. / det;
+        let tvec = r.orig - self.a;
+        let beta = Vec3::dot(tvec, pvec) * inv_det;
+        if !(0. ..=1.).contains(&beta) {
+            return None;
+        }
+        let qvec = Vec3::cross(tvec, self.e1);
+        let gama = Vec3::dot(r.dir, qvec) * inv_det;
+        if gama < 0. || beta + gama > 1. {
+            return None;
+        }
+        let t = Vec3::dot(self.e2, qvec) * inv_det;
+        if t.is_nan() || t < t_min || t > t_max {
             return None;
         }
 
-        let a1 = self.a.x - self.b.x;
-        let b1 = self.a.x - self.c.x;
-        let c1 = self.a.x - p.x;
-        let a2 = self.a.y - self.b.y;
-        let b2 = self.a.y - self.c.y;
-        let c2 = self.a.y - p.y;
-        let beta = (c1 * b2 - b1 * c2) / (a1 * b2 - b1 * a2);
-        let gama = (a1 * c2 - a2 * c1) / (a1 * b2 - b1 * a2);
-
+        let n = self.get_normal();
+        let p = r.at(t);
         let mut rec = HitRecord::new(p, n, t, beta, gama, true, &self.mp);
         rec.set_face_normal(r, n);
         Some(rec)
@@ -90,4 +96,108 @@ impl<M: Material> Hittable for Triangle<M> {
             ),
         ))
     }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        if let Some(rec) = self.hit(Ray::new(o, v, 0.), 0.001, INFINITY) {
+            let solid_angle = spherical_triangle_area(self.a - o, self.b - o, self.c - o);
+            if (MIN_SPHERICAL_AREA..MAX_SPHERICAL_AREA).contains(&solid_angle) {
+                return 1. / solid_angle;
+            }
+            let dis_sqr = rec.t * rec.t * v.length_sqr();
+            let cos = (Vec3::dot(v, rec.normal) / v.length()).abs();
+            dis_sqr / (cos * self.area())
+        } else {
+            0.
+        }
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let (a, b, c) = (self.a - o, self.b - o, self.c - o);
+        let solid_angle = spherical_triangle_area(a, b, c);
+        if (MIN_SPHERICAL_AREA..MAX_SPHERICAL_AREA).contains(&solid_angle) {
+            if let Some(direction) = sample_spherical_triangle(
+                a.to_unit(),
+                b.to_unit(),
+                c.to_unit(),
+                rng.gen(),
+                rng.gen(),
+            ) {
+                return direction;
+            }
+        }
+        let mut r1: f64 = rng.gen();
+        let mut r2: f64 = rng.gen();
+        if r1 + r2 > 1. {
+            r1 = 1. - r1;
+            r2 = 1. - r2;
+        }
+        self.a + (self.b - self.a) * r1 + (self.c - self.a) * r2 - o
+    }
+
+    fn power(&self) -> f64 {
+        self.area() * self.mp.radiant_exitance()
+    }
+}
+
+// Tiny triangles are sampled by area to avoid precision problems, nearly
+// hemispherical ones too since the spherical sampling gets unstable.
+const MIN_SPHERICAL_AREA: f64 = 3e-4;
+const MAX_SPHERICAL_AREA: f64 = 6.22;
+
+// Solid angle of the triangle seen from the origin.
+fn spherical_triangle_area(a: Vec3, b: Vec3, c: Vec3) -> f64 {
+    let (a, b, c) = (a.to_unit(), b.to_unit(), c.to_unit());
+    let numerator = Vec3::dot(a, Vec3::cross(b, c)).abs();
+    let denominator = 1. + Vec3::dot(a, b) + Vec3::dot(a, c) + Vec3::dot(b, c);
+    (2. * numerator.atan2(denominator)).abs()
+}
+
+fn gram_schmidt(v: Vec3, w: Vec3) -> Vec3 {
+    v - w * Vec3::dot(v, w)
+}
+
+fn angle_between(v1: Vec3, v2: Vec3) -> f64 {
+    if Vec3::dot(v1, v2) < 0. {
+        PI - 2. * ((v1 + v2).length() / 2.).clamp(-1., 1.).asin()
+    } else {
+        2. * ((v2 - v1).length() / 2.).clamp(-1., 1.).asin()
+    }
+}
+
+// Arvo's uniform sampling of a spherical triangle, given as unit vectors.
+#[allow(clippy::many_single_char_names)]
+fn sample_spherical_triangle(a: Vec3, b: Vec3, c: Vec3, u0: f64, u1: f64) -> Option<Vec3> {
+    let n_ab = Vec3::cross(a, b);
+    let n_bc = Vec3::cross(b, c);
+    let n_ca = Vec3::cross(c, a);
+    if n_ab.length_sqr() == 0. || n_bc.length_sqr() == 0. || n_ca.length_sqr() == 0. {
+        return None;
+    }
+    let (n_ab, n_bc, n_ca) = (n_ab.to_unit(), n_bc.to_unit(), n_ca.to_unit());
+
+    let alpha = angle_between(n_ab, -n_ca);
+    let beta = angle_between(n_bc, -n_ab);
+    let gamma = angle_between(n_ca, -n_bc);
+
+    // Pick the sub-triangle with the sampled area, it fixes the new vertex on the arc ac
+    let area_pi = alpha + beta + gamma;
+    let sampled = u0 * (area_pi - PI);
+    let (sin_alpha, cos_alpha) = alpha.sin_cos();
+    let (s, t) = (sampled - alpha).sin_cos();
+    let u = t - cos_alpha;
+    let v = s + sin_alpha * Vec3::dot(a, b);
+    let cos_bp = (((v * t - u * s) * cos_alpha - v) / ((v * s + u * t) * sin_alpha)).clamp(-1., 1.);
+    let sin_bp = (1. - cos_bp * cos_bp).max(0.).sqrt();
+    let cp = a * cos_bp + gram_schmidt(c, a).to_unit() * sin_bp;
+
+    // Then sample along the arc from b to the new vertex
+    let cos_theta = 1. - u1 * (1. - Vec3::dot(cp, b));
+    let sin_theta = (1. - cos_theta * cos_theta).max(0.).sqrt();
+    let direction = b * cos_theta + gram_schmidt(cp, b).to_unit() * sin_theta;
+    if direction.x.is_nan() || direction.y.is_nan() || direction.z.is_nan() {
+        None
+    } else {
+        Some(direction)
+    }
 }
@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use gltf::camera::Projection;
+use gltf::mesh::Mode;
+
+use crate::{
+    basic::{
+        camera::Camera,
+        transform::Transform,
+        vec::{Color, Point3, Vec3},
+    },
+    hittable::{
+        instance::Instance,
+        mesh::{MeshData, TriangleMesh},
+        Hittable, HittableList,
+    },
+    material::{Material, Pbr},
+    texture::{ImageTexture, SolidColor, Texture},
+};
+
+use super::ImportError;
+
+pub struct GltfScene {
+    pub world: HittableList,
+    pub cameras: Vec<GltfCamera>,
+}
+
+// A perspective camera placed by its node.
+#[derive(Clone, Copy)]
+pub struct GltfCamera {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    pub vfov: f64,
+}
+
+impl GltfCamera {
+    // `aspect_ratio` is that of the output image, the file's own is ignored so that
+    // the picture is not stretched.
+    pub fn camera(&self, aspect_ratio: f64, time0: f64, time1: f64) -> Camera {
+        let focus_dist = (self.lookat - self.lookfrom).length();
+        Camera::new(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            0.,
+            focus_dist,
+            time0,
+            time1,
+        )
+    }
+}
+
+// Loads the default scene of a .gltf or .glb file. Buffers and images are read from
+// the file itself or from files next to it, never from the network.
+pub fn load_gltf(file_name: &str) -> Result<GltfScene, ImportError> {
+    let (document, buffers, images) =
+        gltf::import(file_name).map_err(|e| ImportError::Gltf(file_name.to_string(), e))?;
+    let malformed = |msg: String| ImportError::Malformed(file_name.to_string(), msg);
+
+    let textures: Vec<Arc<dyn Texture>> = images
+        .iter()
+        .map(|image| Arc::new(convert_image(image)) as Arc<dyn Texture>)
+        .collect();
+    let materials: Vec<Arc<dyn Material>> = document
+        .materials()
+        .map(|m| convert_material(&m, &textures))
+        .collect();
+    let mut default_material = None;
+
+    // Every primitive becomes one mesh, shared by all the nodes using it
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                return Err(malformed(format!(
+                    "mesh {} has {:?} primitives, only triangles are supported",
+                    mesh.index(),
+                    primitive.mode()
+                )));
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions: Vec<Point3> = reader
+                .read_positions()
+                .ok_or_else(|| malformed(format!("mesh {} has no positions", mesh.index())))?
+                .map(|p| Point3::new(p[0] as f64, p[1] as f64, p[2] as f64))
+                .collect();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            if indices.len() % 3 != 0 || indices.iter().any(|&i| i as usize >= positions.len()) {
+                return Err(malformed(format!("mesh {} has bad indices", mesh.index())));
+            }
+
+            let mut data = MeshData::new(
+                positions,
+                indices
+                    .chunks_exact(3)
+                    .map(|f| [f[0], f[1], f[2]])
+                    .collect(),
+            );
+            if let Some(normals) = reader.read_normals() {
+                data.normals = normals
+                    .map(|n| Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64).into())
+                    .collect();
+            }
+            // glTF puts v = 0 at the top of the image
+            if let Some(uvs) = reader.read_tex_coords(0) {
+                data.uvs = uvs
+                    .into_f32()
+                    .map(|t| (t[0] as f64, 1. - t[1] as f64))
+                    .collect();
+            }
+            let mp = match primitive.material().index() {
+                Some(i) => materials[i].clone(),
+                None => default_material
+                    .get_or_insert_with(|| convert_material(&primitive.material(), &textures))
+                    .clone(),
+            };
+            primitives.push(Arc::new(TriangleMesh::new(Arc::new(data), mp)) as Arc<dyn Hittable>);
+        }
+        meshes.push(primitives);
+    }
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| malformed("no scene".to_string()))?;
+    let mut result = GltfScene {
+        world: HittableList::default(),
+        cameras: Vec::new(),
+    };
+    for node in scene.nodes() {
+        add_node(&node, &Transform::identity(), &meshes, &mut result);
+    }
+    Ok(result)
+}
+
+fn add_node(
+    node: &gltf::Node,
+    parent: &Transform,
+    meshes: &[Vec<Arc<dyn Hittable>>],
+    scene: &mut GltfScene,
+) {
+    // glTF matrices are column-major
+    let local = node.transform().matrix();
+    let mut m = [[0.; 4]; 4];
+    for (r, row) in m.iter_mut().enumerate() {
+        for (c, value) in row.iter_mut().enumerate() {
+            *value = local[c][r] as f64;
+        }
+    }
+    // Nodes scaled down to nothing hide their whole subtree
+    let transform = match Transform::new(m) {
+        Some(local) => *parent * local,
+        None => return,
+    };
+
+    if let Some(mesh) = node.mesh() {
+        for primitive in &meshes[mesh.index()] {
+            scene
+                .world
+                .add(Arc::new(Instance::new(primitive.clone(), transform)));
+        }
+    }
+    if let Some(camera) = node.camera() {
+        // Orthographic cameras have no counterpart in `Camera`
+        if let Projection::Perspective(perspective) = camera.projection() {
+            scene.cameras.push(GltfCamera {
+                lookfrom: transform.point(Point3::new(0., 0., 0.)),
+                lookat: transform.point(Point3::new(0., 0., -1.)),
+                vup: transform.vector(Vec3::new(0., 1., 0.)),
+                vfov: (perspective.yfov() as f64).to_degrees(),
+            });
+        }
+    }
+    for child in node.children() {
+        add_node(&child, &transform, meshes, scene);
+    }
+}
+
+fn convert_image(image: &gltf::image::Data) -> ImageTexture {
+    use gltf::image::Format;
+
+    let channels = match image.format {
+        Format::R8 => 1,
+        Format::R8G8 => 2,
+        Format::R8G8B8 | Format::B8G8R8 => 3,
+        Format::R8G8B8A8 | Format::B8G8R8A8 => 4,
+        Format::R16 => 2,
+        Format::R16G16 => 4,
+        Format::R16G16B16 => 6,
+        Format::R16G16B16A16 => 8,
+    };
+    let pixels = image
+        .pixels
+        .chunks_exact(channels)
+        .map(|p| match image.format {
+            Format::R8 => [p[0], p[0], p[0]],
+            Format::R8G8 => [p[0], p[1], 0],
+            Format::R8G8B8 | Format::R8G8B8A8 => [p[0], p[1], p[2]],
+            Format::B8G8R8 | Format::B8G8R8A8 => [p[2], p[1], p[0]],
+            // Keep the high bytes of little-endian 16-bit channels
+            Format::R16 => [p[1], p[1], p[1]],
+            Format::R16G16 => [p[1], p[3], 0],
+            Format::R16G16B16 | Format::R16G16B16A16 => [p[1], p[3], p[5]],
+        })
+        .collect();
+    ImageTexture::new_pixels(image.width, image.height, pixels)
+}
+
+fn convert_material(m: &gltf::Material, textures: &[Arc<dyn Texture>]) -> Arc<dyn Material> {
+    let white: Arc<dyn Texture> = Arc::new(SolidColor::new(Color::new(1., 1., 1.)));
+    let texture = |info: Option<gltf::texture::Info>| match info {
+        Some(info) => textures[info.texture().source().index()].clone(),
+        None => white.clone(),
+    };
+
+    let pbr = m.pbr_metallic_roughness();
+    let base_color = pbr.base_color_factor();
+    let emissive = m.emissive_factor();
+    Arc::new(Pbr::new(
+        texture(pbr.base_color_texture()),
+        Color::new(
+            base_color[0] as f64,
+            base_color[1] as f64,
+            base_color[2] as f64,
+        ),
+        texture(pbr.metallic_roughness_texture()),
+        pbr.metallic_factor() as f64,
+        pbr.roughness_factor() as f64,
+        texture(m.emissive_texture()),
+        Color::new(emissive[0] as f64, emissive[1] as f64, emissive[2] as f64),
+    ))
+}
@@ -0,0 +1,31 @@
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ImportError {
+    Obj(String, tobj::LoadError),
+    Mtl(String, tobj::LoadError),
+    Io(String, std::io::Error),
+    Gltf(String, ::gltf::Error),
+    Texture(String, image::ImageError),
+    Malformed(String, String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Obj(file, e) => write!(f, "{}: {}", file, e),
+            ImportError::Mtl(file, e) => write!(f, "material library of {}: {}", file, e),
+            ImportError::Io(file, e) => write!(f, "{}: {}", file, e),
+            ImportError::Gltf(file, e) => write!(f, "{}: {}", file, e),
+            ImportError::Texture(file, e) => write!(f, "texture {}: {}", file, e),
+            ImportError::Malformed(file, msg) => write!(f, "{}: {}", file, msg),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
@@ -0,0 +1,174 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{
+    basic::vec::{Color, Point3, Vec3},
+    hittable::mesh::{MeshData, TriangleMesh},
+    material::{BumpMap, Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    texture::ImageTexture,
+};
+
+use super::ImportError;
+
+// One `o`/`g`/`usemtl` group of an OBJ file, in the coordinates of the file.
+pub struct ObjObject {
+    pub mesh: TriangleMesh<Arc<dyn Material>>,
+}
+
+// The geometry is still usable without its material library. If that fails to load,
+// every group gets the default material and the error comes back next to the objects.
+pub fn load_obj(file_name: &str) -> Result<(Vec<ObjObject>, Option<ImportError>), ImportError> {
+    let (models, materials) = tobj::load_obj(
+        file_name,
+        &tobj::LoadOptions {
+            single_index: true,
+            triangulate: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| ImportError::Obj(file_name.to_string(), e))?;
+
+    let dir = Path::new(file_name)
+        .parent()
+        .unwrap_or_else(|| Path::new(""));
+    let (materials, warning) = match materials {
+        Ok(materials) => (
+            Some(
+                materials
+                    .iter()
+                    .map(|m| convert_material(m, dir))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None,
+        ),
+        Err(e) => (None, Some(ImportError::Mtl(file_name.to_string(), e))),
+    };
+    let default_material: Arc<dyn Material> =
+        Arc::new(Lambertian::new(Color::new(0.78, 0.78, 0.78)));
+
+    let mut objects = Vec::new();
+    for m in models {
+        let mp = match (&materials, m.mesh.material_id) {
+            (Some(materials), Some(id)) => materials.get(id).cloned().ok_or_else(|| {
+                ImportError::Malformed(
+                    file_name.to_string(),
+                    format!("object {} uses missing material {}", m.name, id),
+                )
+            })?,
+            _ => default_material.clone(),
+        };
+        let data = convert_mesh(&m.mesh).map_err(|msg| {
+            ImportError::Malformed(file_name.to_string(), format!("object {}: {}", m.name, msg))
+        })?;
+        objects.push(ObjObject {
+            mesh: TriangleMesh::new(Arc::new(data), mp),
+        });
+    }
+    Ok((objects, warning))
+}
+
+fn convert_mesh(mesh: &tobj::Mesh) -> Result<MeshData, String> {
+    let positions: Vec<Point3> = mesh
+        .positions
+        .chunks_exact(3)
+        .map(|p| Point3::new(p[0] as f64, p[1] as f64, p[2] as f64))
+        .collect();
+    if mesh.indices.len() % 3 != 0 {
+        return Err("faces are not triangles".to_string());
+    }
+    if let Some(&i) = mesh
+        .indices
+        .iter()
+        .find(|&&i| i as usize >= positions.len())
+    {
+        return Err(format!("vertex index {} out of range", i));
+    }
+    let indices = mesh
+        .indices
+        .chunks_exact(3)
+        .map(|f| [f[0], f[1], f[2]])
+        .collect();
+
+    let mut data = MeshData::new(positions, indices);
+    data.normals = mesh
+        .normals
+        .chunks_exact(3)
+        .map(|n| Vec3::new(n[0] as f64, n[1] as f64, n[2] as f64).into())
+        .collect();
+    data.uvs = mesh
+        .texcoords
+        .chunks_exact(2)
+        .map(|t| (t[0] as f64, t[1] as f64))
+        .collect();
+    Ok(data)
+}
+
+fn color(c: [f32; 3]) -> Color {
+    Color::new(c[0] as f64, c[1] as f64, c[2] as f64)
+}
+
+fn load_texture(dir: &Path, file_name: &str) -> Result<ImageTexture, ImportError> {
+    let path = dir.join(file_name);
+    let path = path.to_string_lossy();
+    ImageTexture::open(&path).map_err(|e| ImportError::Texture(path.to_string(), e))
+}
+
+fn convert_material(m: &tobj::Material, dir: &Path) -> Result<Arc<dyn Material>, ImportError> {
+    let malformed = |msg: String| ImportError::Malformed(m.name.clone(), msg);
+
+    // Emitters
+    if let Some(ke) = m.unknown_param.get("Ke") {
+        let ke: Vec<f64> = ke
+            .split_whitespace()
+            .map(|x| x.parse().map_err(|_| malformed(format!("bad Ke {}", ke))))
+            .collect::<Result<_, _>>()?;
+        if ke.len() != 3 {
+            return Err(malformed(format!("bad Ke {:?}", ke)));
+        }
+        if ke.iter().any(|&x| x > 0.) {
+            return Ok(Arc::new(DiffuseLight::new(Color::new(ke[0], ke[1], ke[2]))));
+        }
+    }
+
+    // Glass, for transparent materials or the refraction illumination models
+    let illum = m.illumination_model.unwrap_or(2);
+    if m.dissolve < 1. || matches!(illum, 4 | 6 | 7 | 9) {
+        let ir = if m.optical_density > 1. {
+            m.optical_density as f64
+        } else {
+            1.5
+        };
+        return Ok(Arc::new(Dielectric::new(ir)));
+    }
+
+    let base: Arc<dyn Material> = if illum == 3 || illum == 5 {
+        // Phong exponent to roughly the same lobe width
+        let fuzz = (2. / (m.shininess as f64 + 2.)).sqrt();
+        Arc::new(Metal::new(color(m.specular), fuzz))
+    } else if !m.diffuse_texture.is_empty() {
+        Arc::new(Lambertian::new_arc(load_texture(dir, &m.diffuse_texture)?))
+    } else {
+        Arc::new(Lambertian::new(color(m.diffuse)))
+    };
+
+    if m.normal_texture.is_empty() {
+        return Ok(base);
+    }
+    // map_bump [-bm scale] file, heights are in object units
+    let bad_map_bump = || malformed(format!("bad map_bump {}", m.normal_texture));
+    let mut words = m.normal_texture.split_whitespace();
+    let mut scale = 1.;
+    let mut file_name = None;
+    while let Some(word) = words.next() {
+        if word == "-bm" {
+            scale = words
+                .next()
+                .and_then(|x| x.parse().ok())
+                .ok_or_else(bad_map_bump)?;
+        } else {
+            file_name = Some(word);
+        }
+    }
+    let height = load_texture(dir, file_name.ok_or_else(bad_map_bump)?)?;
+    Ok(Arc::new(BumpMap::new(base, height, scale)))
+}
@@ -0,0 +1,402 @@
+use std::fs;
+
+use crate::{
+    basic::vec::{Color, Point3, Vec3},
+    hittable::mesh::MeshData,
+};
+
+use super::ImportError;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+}
+
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// Reads the values of the body one by one, whatever the format.
+struct Body<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Body<'a> {
+    fn read(&mut self, ty: Scalar) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let rest = &self.bytes[self.pos..];
+            let start = rest
+                .iter()
+                .position(|c| !c.is_ascii_whitespace())
+                .ok_or("unexpected end of file")?;
+            let len = rest[start..]
+                .iter()
+                .position(|c| c.is_ascii_whitespace())
+                .unwrap_or(rest.len() - start);
+            self.pos += start + len;
+            let word = String::from_utf8_lossy(&rest[start..start + len]);
+            return word.parse().map_err(|_| format!("bad number {}", word));
+        }
+
+        let size = ty.size();
+        if self.pos + size > self.bytes.len() {
+            return Err("unexpected end of file".to_string());
+        }
+        let mut b = [0u8; 8];
+        b[..size].copy_from_slice(&self.bytes[self.pos..self.pos + size]);
+        if self.format == Format::BinaryBigEndian {
+            b[..size].reverse();
+        }
+        self.pos += size;
+        Ok(match ty {
+            Scalar::I8 => b[0] as i8 as f64,
+            Scalar::U8 => b[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(b),
+        })
+    }
+}
+
+// Loads the `vertex` and `face` elements of an ASCII or binary PLY file. Vertices may carry
+// normals (nx, ny, nz), texture coordinates (u, v or s, t) and colors (red, green, blue).
+pub fn load_ply(file_name: &str) -> Result<MeshData, ImportError> {
+    let bytes = fs::read(file_name).map_err(|e| ImportError::Io(file_name.to_string(), e))?;
+    parse(&bytes).map_err(|msg| ImportError::Malformed(file_name.to_string(), msg))
+}
+
+fn parse(bytes: &[u8]) -> Result<MeshData, String> {
+    // Header
+    let end = b"end_header";
+    let header_end = bytes
+        .windows(end.len())
+        .position(|w| w == end)
+        .ok_or("no end_header")?;
+    let mut body_start = header_end + end.len();
+    if bytes.get(body_start) == Some(&b'\r') {
+        body_start += 1;
+    }
+    if bytes.get(body_start) == Some(&b'\n') {
+        body_start += 1;
+    }
+    let header = String::from_utf8_lossy(&bytes[..header_end]);
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err("not a PLY file".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", f, _] => {
+                format = Some(match *f {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(format!("unknown format {}", f)),
+                })
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| format!("bad count {}", count))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let property = Property::List(
+                    name.to_string(),
+                    Scalar::parse(count).ok_or(format!("unknown type {}", count))?,
+                    Scalar::parse(item).ok_or(format!("unknown type {}", item))?,
+                );
+                elements
+                    .last_mut()
+                    .ok_or("property before element")?
+                    .properties
+                    .push(property);
+            }
+            ["property", ty, name] => {
+                let property = Property::Scalar(
+                    name.to_string(),
+                    Scalar::parse(ty).ok_or(format!("unknown type {}", ty))?,
+                );
+                elements
+                    .last_mut()
+                    .ok_or("property before element")?
+                    .properties
+                    .push(property);
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(format!("bad header line {}", line)),
+        }
+    }
+
+    // Body
+    let mut body = Body {
+        format: format.ok_or("no format")?,
+        bytes: &bytes[body_start..],
+        pos: 0,
+    };
+    let mut data = MeshData::default();
+    for element in &elements {
+        let is_vertex = element.name == "vertex";
+        let is_face = element.name == "face";
+        for _ in 0..element.count {
+            let mut position = [0.; 3];
+            let mut normal = [0.; 3];
+            let mut uv = (0., 0.);
+            let mut color = [0.; 3];
+            for property in &element.properties {
+                match property {
+                    Property::Scalar(name, ty) => {
+                        let value = body.read(*ty)?;
+                        if !is_vertex {
+                            continue;
+                        }
+                        // 8-bit colors are in [0, 255], the others in [0, 1]
+                        let channel = match ty {
+                            Scalar::U8 => value / 255.,
+                            _ => value,
+                        };
+                        match name.as_str() {
+                            "x" => position[0] = value,
+                            "y" => position[1] = value,
+                            "z" => position[2] = value,
+                            "nx" => normal[0] = value,
+                            "ny" => normal[1] = value,
+                            "nz" => normal[2] = value,
+                            "u" | "s" | "texture_u" | "texture_s" => uv.0 = value,
+                            "v" | "t" | "texture_v" | "texture_t" => uv.1 = value,
+                            "red" | "r" => color[0] = channel,
+                            "green" | "g" => color[1] = channel,
+                            "blue" | "b" => color[2] = channel,
+                            _ => {}
+                        }
+                    }
+                    Property::List(name, count, item) => {
+                        let count = body.read(*count)? as usize;
+                        let mut indices = Vec::with_capacity(count);
+                        for _ in 0..count {
+                            indices.push(body.read(*item)? as u32);
+                        }
+                        if is_face && (name == "vertex_indices" || name == "vertex_index") {
+                            // Fan out polygons into triangles
+                            for i in 2..indices.len() {
+                                data.indices.push([indices[0], indices[i - 1], indices[i]]);
+                            }
+                        }
+                    }
+                }
+            }
+            if is_vertex {
+                data.positions
+                    .push(Point3::new(position[0], position[1], position[2]).into());
+                data.normals
+                    .push(Vec3::new(normal[0], normal[1], normal[2]).into());
+                data.uvs.push(uv);
+                data.colors.push(Color::new(color[0], color[1], color[2]));
+            }
+        }
+    }
+
+    // Drop the attributes the file does not have
+    let has = |names: &[&str]| {
+        elements.iter().any(|e| {
+            e.name == "vertex"
+                && e.properties.iter().any(|p| match p {
+                    Property::Scalar(name, _) => names.contains(&name.as_str()),
+                    Property::List(..) => false,
+                })
+        })
+    };
+    if !has(&["nx", "ny", "nz"]) {
+        data.normals.clear();
+    }
+    if !has(&[
+        "u",
+        "v",
+        "s",
+        "t",
+        "texture_u",
+        "texture_v",
+        "texture_s",
+        "texture_t",
+    ]) {
+        data.uvs.clear();
+    }
+    if !has(&["red", "green", "blue", "r", "g", "b"]) {
+        data.colors.clear();
+    }
+    if let Some(&i) = data
+        .indices
+        .iter()
+        .flatten()
+        .find(|&&i| i as usize >= data.positions.len())
+    {
+        return Err(format!("vertex index {} out of range", i));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_quad_is_fanned() {
+        let text = "ply
+format ascii 1.0
+comment a unit square
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+";
+        let data = parse(text.as_bytes()).unwrap();
+        assert_eq!(data.positions.len(), 4);
+        let p = data.positions[2];
+        assert_eq!((p.x, p.y, p.z), (1., 1., 0.));
+        assert_eq!(data.indices, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(
+            (data.colors[0].x, data.colors[1].y, data.colors[1].z),
+            (1., 1., 0.)
+        );
+        assert!(data.normals.is_empty() && data.uvs.is_empty());
+    }
+
+    #[test]
+    fn binary_little_endian() {
+        let mut bytes = b"ply\r
+format binary_little_endian 1.0\r
+element vertex 3\r
+property float x\r
+property float y\r
+property float z\r
+property double u\r
+property double v\r
+element face 1\r
+property list uchar uint vertex_indices\r
+end_header\r
+"
+        .to_vec();
+        for (p, uv) in [
+            ([0f32, 0., 1.], [0., 0.]),
+            ([1., 0., 1.], [1., 0.]),
+            ([0., 1., 1.], [0f64, 1.]),
+        ]
+        .iter()
+        {
+            for x in p {
+                bytes.extend_from_slice(&x.to_le_bytes());
+            }
+            for x in uv {
+                bytes.extend_from_slice(&x.to_le_bytes());
+            }
+        }
+        bytes.push(3);
+        for i in [0u32, 1, 2].iter() {
+            bytes.extend_from_slice(&i.to_le_bytes());
+        }
+
+        let data = parse(&bytes).unwrap();
+        let p = data.positions[1];
+        assert_eq!((p.x, p.y, p.z), (1., 0., 1.));
+        assert_eq!(data.uvs, vec![(0., 0.), (1., 0.), (0., 1.)]);
+        assert_eq!(data.indices, vec![[0, 1, 2]]);
+        assert!(data.colors.is_empty());
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let text = "ply
+format ascii 1.0
+element vertex 3
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+0 1 0
+3 0 1 3
+";
+        let error = parse(text.as_bytes()).err().unwrap();
+        assert_eq!(error, "vertex index 3 out of range");
+    }
+
+    #[test]
+    fn truncated_body() {
+        let text = "ply
+format ascii 1.0
+element vertex 2
+property float x
+end_header
+0
+";
+        assert!(parse(text.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs;
+
+use crate::{basic::vec::Point3, hittable::mesh::MeshData};
+
+use super::ImportError;
+
+// Loads an ASCII or binary STL file. Facets only store their own corners,
+// so equal corners are merged into shared vertices.
+pub fn load_stl(file_name: &str) -> Result<MeshData, ImportError> {
+    let bytes = fs::read(file_name).map_err(|e| ImportError::Io(file_name.to_string(), e))?;
+    parse(&bytes).map_err(|msg| ImportError::Malformed(file_name.to_string(), msg))
+}
+
+fn parse(bytes: &[u8]) -> Result<MeshData, String> {
+    // Binary files may also start with "solid", so trust the size first
+    let corners = if bytes.len() >= 84
+        && bytes.len() == 84 + 50 * u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize
+    {
+        parse_binary(bytes)
+    } else if bytes.starts_with(b"solid") {
+        parse_ascii(&String::from_utf8_lossy(bytes))?
+    } else {
+        return Err("neither ASCII nor binary STL".to_string());
+    };
+
+    let mut data = MeshData::default();
+    let mut vertices: HashMap<[u64; 3], u32> = HashMap::new();
+    for facet in corners.chunks_exact(3) {
+        let mut face = [0; 3];
+        for (index, p) in face.iter_mut().zip(facet) {
+            let key = [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+            *index = *vertices.entry(key).or_insert_with(|| {
+                data.positions.push((*p).into());
+                data.positions.len() as u32 - 1
+            });
+        }
+        data.indices.push(face);
+    }
+    Ok(data)
+}
+
+fn parse_binary(bytes: &[u8]) -> Vec<Point3> {
+    let float = |at: usize| f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as f64;
+    // Each facet is a normal, three corners and two attribute bytes
+    let count = (bytes.len() - 84) / 50;
+    (0..count)
+        .flat_map(|i| (0..3).map(move |c| 84 + 50 * i + 12 * (c + 1)))
+        .map(|at| Point3::new(float(at), float(at + 4), float(at + 8)))
+        .collect()
+}
+
+fn parse_ascii(text: &str) -> Result<Vec<Point3>, String> {
+    let mut corners = Vec::new();
+    let mut words = text.split_whitespace();
+    while let Some(word) = words.next() {
+        if word != "vertex" {
+            continue;
+        }
+        let mut p = [0.; 3];
+        for value in p.iter_mut() {
+            let word = words.next().ok_or("unexpected end of file")?;
+            *value = word.parse().map_err(|_| format!("bad number {}", word))?;
+        }
+        corners.push(Point3::new(p[0], p[1], p[2]));
+    }
+    if corners.len() % 3 != 0 {
+        return Err("facets must have three vertices".to_string());
+    }
+    Ok(corners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two facets sharing the edge from (1, 0, 0) to (0, 1, 0)
+    const FACETS: [[[f32; 3]; 3]; 2] = [
+        [[0., 0., 0.], [1., 0., 0.], [0., 1., 0.]],
+        [[1., 0., 0.], [1., 1., 0.], [0., 1., 0.]],
+    ];
+
+    fn binary(header: &[u8]) -> Vec<u8> {
+        let mut bytes = header.to_vec();
+        bytes.resize(80, 0);
+        bytes.extend_from_slice(&(FACETS.len() as u32).to_le_bytes());
+        for facet in FACETS.iter() {
+            for value in [0f32, 0., 1.].iter().chain(facet.iter().flatten()) {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&[0, 0]);
+        }
+        bytes
+    }
+
+    fn check(data: &MeshData) {
+        assert_eq!(data.positions.len(), 4);
+        assert_eq!(data.indices, vec![[0, 1, 2], [1, 3, 2]]);
+        let p = data.positions[3];
+        assert_eq!((p.x, p.y, p.z), (1., 1., 0.));
+    }
+
+    #[test]
+    fn ascii() {
+        let text = "solid quad
+            facet normal 0 0 1
+              outer loop
+                vertex 0 0 0
+                vertex 1 0 0
+                vertex 0 1 0
+              endloop
+            endfacet
+            facet normal 0 0 1
+              outer loop
+                vertex 1 0 0
+                vertex 1 1 0
+                vertex 0 1 0
+              endloop
+            endfacet
+            endsolid quad
+            ";
+        check(&parse(text.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn binary_merges_shared_corners() {
+        check(&parse(&binary(b"exported")).unwrap());
+    }
+
+    #[test]
+    fn binary_starting_with_solid() {
+        check(&parse(&binary(b"solid exported by a careless tool")).unwrap());
+    }
+
+    #[test]
+    fn truncated_binary() {
+        let bytes = binary(b"exported");
+        assert!(parse(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn incomplete_ascii_facet() {
+        let text = "solid bad facet outer loop vertex 0 0 0 vertex 1 0 0 endloop endsolid";
+        assert!(parse(text.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use std::f64::INFINITY;
+
+use crate::basic::{
+    onb::Onb,
+    vec::{random_to_sphere, Color, Point3, Vec3},
+};
+
+use super::{Light, LightSample};
+
+#[derive(Clone, Copy)]
+pub struct PointLight {
+    position: Point3,
+    intensity: Color,
+}
+
+impl PointLight {
+    #[allow(dead_code)]
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample_li(&self, p: Point3) -> Option<LightSample> {
+        let direction = self.position - p;
+        let dis_sqr = direction.length_sqr();
+        let dis = dis_sqr.sqrt();
+        Some(LightSample::new(
+            direction / dis,
+            dis,
+            self.intensity / dis_sqr,
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl SpotLight {
+    // Full intensity inside `inner` degrees, fading out smoothly to zero at `outer` degrees.
+    #[allow(dead_code)]
+    pub fn new(position: Point3, lookat: Point3, intensity: Color, inner: f64, outer: f64) -> Self {
+        Self {
+            position,
+            direction: (lookat - position).to_unit(),
+            intensity,
+            cos_inner: inner.to_radians().cos(),
+            cos_outer: outer.to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos: f64) -> f64 {
+        if cos >= self.cos_inner {
+            1.
+        } else if cos <= self.cos_outer {
+            0.
+        } else {
+            let t = (cos - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            t * t * (3. - 2. * t)
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn sample_li(&self, p: Point3) -> Option<LightSample> {
+        let direction = self.position - p;
+        let dis_sqr = direction.length_sqr();
+        let dis = dis_sqr.sqrt();
+        let dir = direction / dis;
+        let falloff = self.falloff(Vec3::dot(-dir, self.direction));
+        if falloff <= 0. {
+            return None;
+        }
+        Some(LightSample::new(
+            dir,
+            dis,
+            self.intensity * falloff / dis_sqr,
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DirectionalLight {
+    uvw: Onb,
+    irradiance: Color,
+    sin_max: f64,
+}
+
+impl DirectionalLight {
+    // `direction` points towards the light. A non-zero `angular_diameter` (in degrees)
+    // turns it into a distant disk, like the sun, which casts soft shadows.
+    #[allow(dead_code)]
+    pub fn new(direction: Vec3, irradiance: Color, angular_diameter: f64) -> Self {
+        Self {
+            uvw: Onb::build_from_w(direction),
+            irradiance,
+            sin_max: (angular_diameter / 2.).to_radians().sin(),
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample_li(&self, _p: Point3) -> Option<LightSample> {
+        let dir = if self.sin_max > 0. {
+            self.uvw
+                .local_vec(random_to_sphere(self.sin_max, 1.))
+                .to_unit()
+        } else {
+            self.uvw.w()
+        };
+        Some(LightSample::new(dir, INFINITY, self.irradiance))
+    }
+}
@@ -0,0 +1,163 @@
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::BufReader;
+
+use image::{codecs::hdr::HdrDecoder, GenericImageView};
+use rand::Rng;
+
+use crate::{
+    basic::{
+        distribution::Distribution2D,
+        ray::Ray,
+        vec::{Color, Point3, Vec3},
+    },
+    hittable::{bvh::aabb::AABB, HitRecord, Hittable},
+};
+
+// An infinitely far away sphere lit by an equirectangular image.
+// It is never hit, rays that miss the world read it through `environment`.
+#[derive(Clone)]
+pub struct EnvironmentLight {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    distribution: Distribution2D,
+    intensity: f64,
+    sin_theta: f64,
+    cos_theta: f64,
+}
+
+impl EnvironmentLight {
+    // `filename` is an equirectangular `.hdr` image, other formats are treated as sRGB.
+    // `rotation` turns the map around the y axis, in degrees.
+    #[allow(dead_code)]
+    pub fn new(filename: &str, rotation: f64, intensity: f64) -> image::ImageResult<Self> {
+        let (width, height, pixels) = if filename.to_lowercase().ends_with(".hdr") {
+            let file = File::open(filename)?;
+            let decoder = HdrDecoder::new(BufReader::new(file))?;
+            let meta = decoder.metadata();
+            let pixels = decoder
+                .read_image_hdr()?
+                .iter()
+                .map(|p| Color::new(p[0] as f64, p[1] as f64, p[2] as f64))
+                .collect();
+            (meta.width as usize, meta.height as usize, pixels)
+        } else {
+            let img = image::open(filename)?;
+            let (width, height) = img.dimensions();
+            let mut pixels = Vec::new();
+            for y in 0..height {
+                for x in 0..width {
+                    let pixel = img.get_pixel(x, y);
+                    pixels.push(Color::new(
+                        (pixel[0] as f64 / 255.).powf(2.2),
+                        (pixel[1] as f64 / 255.).powf(2.2),
+                        (pixel[2] as f64 / 255.).powf(2.2),
+                    ));
+                }
+            }
+            (width as usize, height as usize, pixels)
+        };
+        Ok(Self::new_pixels(width, height, pixels, rotation, intensity))
+    }
+
+    // `pixels` are linear radiance, row by row from the top (+y) of the sphere.
+    pub fn new_pixels(
+        width: usize,
+        height: usize,
+        pixels: Vec<Color>,
+        rotation: f64,
+        intensity: f64,
+    ) -> Self {
+        let mut func = Vec::with_capacity(width * height);
+        for j in 0..height {
+            let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+            for i in 0..width {
+                func.push(pixels[j * width + i].luminance() * sin_theta);
+            }
+        }
+        let radians = rotation.to_radians();
+        Self {
+            width,
+            height,
+            pixels,
+            distribution: Distribution2D::new(&func, width, height),
+            intensity,
+            sin_theta: radians.sin(),
+            cos_theta: radians.cos(),
+        }
+    }
+
+    fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x - self.sin_theta * v.z,
+            v.y,
+            self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    fn to_world(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.cos_theta * v.x + self.sin_theta * v.z,
+            v.y,
+            -self.sin_theta * v.x + self.cos_theta * v.z,
+        )
+    }
+
+    fn direction_uv(&self, direction: Vec3) -> (f64, f64) {
+        let d = self.to_local(direction.to_unit());
+        let theta = d.y.clamp(-1., 1.).acos();
+        let mut phi = d.z.atan2(d.x);
+        if phi < 0. {
+            phi += 2. * PI;
+        }
+        (phi / (2. * PI), theta / PI)
+    }
+
+    pub fn radiance(&self, direction: Vec3) -> Color {
+        let (u, v) = self.direction_uv(direction);
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i] * self.intensity
+    }
+}
+
+impl Hittable for EnvironmentLight {
+    fn hit(&self, _r: Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+        None
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        None
+    }
+
+    fn pdf_value(&self, _o: Point3, v: Vec3) -> f64 {
+        let (u, w) = self.direction_uv(v);
+        let sin_theta = (w * PI).sin();
+        if sin_theta <= 0. {
+            return 0.;
+        }
+        self.distribution.pdf(u, w) / (2. * PI * PI * sin_theta)
+    }
+
+    fn random(&self, _o: Point3) -> Vec3 {
+        let mut rng = rand::thread_rng();
+        let (u, v, _pdf) = self.distribution.sample_continuous(rng.gen(), rng.gen());
+        let theta = v * PI;
+        let phi = u * 2. * PI;
+        self.to_world(Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        ))
+    }
+
+    fn environment(&self, direction: Vec3) -> Color {
+        self.radiance(direction)
+    }
+
+    fn power(&self) -> f64 {
+        // pi times the average radiance over the sphere
+        PI * PI * self.distribution.integral() / 2. * self.intensity
+    }
+}
@@ -0,0 +1,45 @@
+pub mod delta;
+pub mod environment;
+pub mod sampler;
+pub mod sky;
+
+use std::sync::Arc;
+
+use super::basic::vec::{Color, Point3, Vec3};
+
+pub struct LightSample {
+    pub dir: Vec3,
+    pub dis: f64,
+    pub li: Color,
+}
+
+impl LightSample {
+    pub fn new(dir: Vec3, dis: f64, li: Color) -> Self {
+        Self { dir, dis, li }
+    }
+}
+
+// Lights without geometry, they are sampled explicitly by the integrator
+// and never hit by rays. `li` in the sample is already divided by its pdf.
+pub trait Light: Send + Sync {
+    fn sample_li(&self, p: Point3) -> Option<LightSample>;
+}
+
+#[derive(Clone)]
+pub struct LightList {
+    pub objects: Vec<Arc<dyn Light>>,
+}
+
+impl Default for LightList {
+    fn default() -> Self {
+        Self {
+            objects: Vec::new(),
+        }
+    }
+}
+
+impl LightList {
+    pub fn add(&mut self, object: Arc<dyn Light>) {
+        self.objects.push(object);
+    }
+}
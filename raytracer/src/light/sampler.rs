@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::{
+    basic::{
+        distribution::Distribution1D,
+        ray::Ray,
+        vec::{Color, Point3, Vec3},
+    },
+    hittable::{bvh::aabb::AABB, HitRecord, Hittable, HittableList},
+};
+
+// Picks lights in proportion to their emitted power instead of uniformly.
+#[derive(Clone)]
+pub struct LightSampler {
+    objects: Vec<Arc<dyn Hittable>>,
+    distribution: Distribution1D,
+}
+
+impl LightSampler {
+    // `scene_radius` turns the power of unbounded lights (per unit area) into a total power.
+    pub fn new(lights: &HittableList, scene_radius: f64) -> Self {
+        let mut powers: Vec<f64> = lights
+            .objects
+            .iter()
+            .map(|light| {
+                let power = light.power();
+                if light.bounding_box(0., 1.).is_none() {
+                    power * scene_radius * scene_radius
+                } else {
+                    power
+                }
+            })
+            .collect();
+
+        // Lights that cannot tell their power get the average one, so they are still sampled.
+        let known: Vec<f64> = powers.iter().cloned().filter(|p| *p > 0.).collect();
+        let fallback = if known.is_empty() {
+            1.
+        } else {
+            known.iter().sum::<f64>() / known.len() as f64
+        };
+        for power in powers.iter_mut() {
+            if *power <= 0. || !power.is_finite() {
+                *power = fallback;
+            }
+        }
+
+        Self {
+            objects: lights.objects.clone(),
+            distribution: Distribution1D::new(&powers),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl Hittable for LightSampler {
+    fn hit(&self, r: Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut temp_rec: Option<HitRecord> = None;
+        let mut closest_so_far = t_max;
+        for object in &self.objects {
+            if let Some(rec) = object.hit(r, t_min, closest_so_far) {
+                closest_so_far = rec.t;
+                temp_rec = Some(rec);
+            }
+        }
+        temp_rec
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        None
+    }
+
+    fn pdf_value(&self, o: Point3, v: Vec3) -> f64 {
+        let mut sum = 0.;
+        for (i, object) in self.objects.iter().enumerate() {
+            let pmf = self.distribution.discrete_pdf(i);
+            if pmf > 0. {
+                sum += pmf * object.pdf_value(o, v);
+            }
+        }
+        sum
+    }
+
+    fn random(&self, o: Point3) -> Vec3 {
+        let (target, _pmf) = self.distribution.sample_discrete(rand::thread_rng().gen());
+        self.objects[target].random(o)
+    }
+
+    fn environment(&self, direction: Vec3) -> Color {
+        let mut sum = Color::new(0., 0., 0.);
+        for object in &self.objects {
+            sum += object.environment(direction);
+        }
+        sum
+    }
+
+    fn power(&self) -> f64 {
+        self.objects.iter().map(|object| object.power()).sum()
+    }
+}
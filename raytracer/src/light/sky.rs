@@ -0,0 +1,305 @@
+use std::f64::consts::PI;
+
+use crate::{
+    basic::{
+        onb::Onb,
+        ray::Ray,
+        vec::{random_to_sphere, Color, Point3, Vec3},
+    },
+    hittable::{bvh::aabb::AABB, HitRecord, Hittable},
+};
+
+use super::environment::EnvironmentLight;
+
+const SUN_ANGULAR_DIAMETER: f64 = 0.53;
+const SUN_LUMINANCE: f64 = 1.6e6;
+
+// Preetham et al. "A Practical Analytic Model for Daylight".
+// Luminances are in kcd/m^2 before being scaled by `intensity`.
+#[derive(Clone, Copy)]
+pub struct Sky {
+    sun_direction: Vec3,
+    turbidity: f64,
+    ground_albedo: Color,
+    intensity: f64,
+    zenith: [f64; 3],
+    perez: [[f64; 5]; 3],
+    perez_zenith: [f64; 3],
+}
+
+impl Sky {
+    // `sun_direction` points towards the sun, `turbidity` goes from 2 (clear) to 10 (hazy).
+    #[allow(dead_code)]
+    pub fn new(sun_direction: Vec3, turbidity: f64, ground_albedo: Color, intensity: f64) -> Self {
+        let sun_direction = sun_direction.to_unit();
+        let t = turbidity;
+        let theta_s = sun_direction.y.clamp(-1., 1.).acos().min(PI / 2.);
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta_s);
+        let zenith_y = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.);
+        let ts = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.];
+        let zenith_chromaticity = |m: [[f64; 4]; 3]| {
+            let mut res = 0.;
+            for (row, tt) in m.iter().zip([t * t, t, 1.].iter()) {
+                for (c, s) in row.iter().zip(ts.iter()) {
+                    res += tt * c * s;
+                }
+            }
+            res
+        };
+        let zenith_x = zenith_chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let zenith_yy = zenith_chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        // The distribution at the zenith, where cos(theta) is 1 and the sun is theta_s away
+        let mut perez_zenith = [0.; 3];
+        for (i, p) in perez.iter().enumerate() {
+            perez_zenith[i] = Sky::perez_function(p, 1., theta_s);
+        }
+
+        Self {
+            sun_direction,
+            turbidity,
+            ground_albedo,
+            intensity,
+            zenith: [zenith_y, zenith_x, zenith_yy],
+            perez,
+            perez_zenith,
+        }
+    }
+
+    // Places the sun from the day of the year (1 to 365), the local solar time in hours
+    // and the latitude in degrees. North is -z and east is +x.
+    #[allow(dead_code)]
+    pub fn new_time(
+        day: u32,
+        hour: f64,
+        latitude: f64,
+        turbidity: f64,
+        ground_albedo: Color,
+        intensity: f64,
+    ) -> Self {
+        Sky::new(
+            sun_direction(day, hour, latitude),
+            turbidity,
+            ground_albedo,
+            intensity,
+        )
+    }
+
+    fn perez_function(p: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+        (1. + p[0] * (p[1] / cos_theta.max(0.01)).exp())
+            * (1. + p[2] * (p[3] * gamma).exp() + p[4] * gamma.cos().powi(2))
+    }
+
+    // Radiance of the sky alone, the sun disk is handled by `SunLight`.
+    #[allow(clippy::needless_range_loop)]
+    pub fn sky_radiance(&self, direction: Vec3) -> Color {
+        let d = direction.to_unit();
+        let cos_theta = d.y.max(0.);
+        let gamma = Vec3::dot(d, self.sun_direction).clamp(-1., 1.).acos();
+        let mut xyy = [0.; 3];
+        for i in 0..3 {
+            xyy[i] = self.zenith[i] * Sky::perez_function(&self.perez[i], cos_theta, gamma)
+                / self.perez_zenith[i];
+        }
+        xyy_to_rgb(xyy[1], xyy[2], xyy[0].max(0.)) * self.intensity
+    }
+
+    pub fn sun_radiance(&self) -> Color {
+        let cos_theta = self.sun_direction.y;
+        if cos_theta <= 0. {
+            return Color::new(0., 0., 0.);
+        }
+        // Kasten and Young's relative air mass
+        let theta = cos_theta.acos().to_degrees();
+        let air_mass = 1. / (cos_theta + 0.15 * (93.885 - theta).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = |lambda: f64| {
+            let rayleigh = 0.008735 * lambda.powf(-4.08);
+            let aerosol = beta * lambda.powf(-1.3);
+            (-air_mass * (rayleigh + aerosol)).exp()
+        };
+        Color::new(
+            transmittance(0.65),
+            transmittance(0.57),
+            transmittance(0.475),
+        ) * SUN_LUMINANCE
+            * self.intensity
+    }
+
+    // Tabulates the sky into an importance sampled environment light.
+    // The lower hemisphere shows a diffuse ground lit by the sky and the sun.
+    #[allow(dead_code)]
+    pub fn sky_light(&self, width: usize, height: usize) -> EnvironmentLight {
+        let direction = |i: usize, j: usize| {
+            let theta = PI * (j as f64 + 0.5) / height as f64;
+            let phi = 2. * PI * (i as f64 + 0.5) / width as f64;
+            Vec3::new(
+                theta.sin() * phi.cos(),
+                theta.cos(),
+                theta.sin() * phi.sin(),
+            )
+        };
+
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut irradiance = Color::new(0., 0., 0.);
+        for j in 0..height {
+            for i in 0..width {
+                let d = direction(i, j);
+                if d.y > 0. {
+                    let radiance = self.sky_radiance(d);
+                    let solid_angle = (PI / height as f64)
+                        * (2. * PI / width as f64)
+                        * (PI * (j as f64 + 0.5) / height as f64).sin();
+                    irradiance += radiance * d.y * solid_angle;
+                    pixels.push(radiance);
+                } else {
+                    pixels.push(Color::new(0., 0., 0.));
+                }
+            }
+        }
+
+        let sun_solid_angle = 2. * PI * (1. - (SUN_ANGULAR_DIAMETER / 2.).to_radians().cos());
+        irradiance += self.sun_radiance() * sun_solid_angle * self.sun_direction.y.max(0.);
+        let ground = self.ground_albedo * irradiance / PI;
+        for j in 0..height {
+            for i in 0..width {
+                if direction(i, j).y <= 0. {
+                    pixels[j * width + i] = ground;
+                }
+            }
+        }
+
+        EnvironmentLight::new_pixels(width, height, pixels, 0., 1.)
+    }
+
+    #[allow(dead_code)]
+    pub fn sun_light(&self) -> SunLight {
+        SunLight::new(
+            self.sun_direction,
+            self.sun_radiance(),
+            SUN_ANGULAR_DIAMETER,
+        )
+    }
+}
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0. {
+        return Color::new(0., 0., 0.);
+    }
+    let cx = x / y * luminance;
+    let cz = (1. - x - y) / y * luminance;
+    Color::new(
+        (3.2406 * cx - 1.5372 * luminance - 0.4986 * cz).max(0.),
+        (-0.9689 * cx + 1.8758 * luminance + 0.0415 * cz).max(0.),
+        (0.0557 * cx - 0.2040 * luminance + 1.0570 * cz).max(0.),
+    )
+}
+
+pub fn sun_direction(day: u32, hour: f64, latitude: f64) -> Vec3 {
+    let declination = (23.44 * (2. * PI * (284. + day as f64) / 365.).sin()).to_radians();
+    let hour_angle = (15. * (hour - 12.)).to_radians();
+    let latitude = latitude.to_radians();
+
+    let sin_altitude =
+        latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    let altitude = sin_altitude.clamp(-1., 1.).asin();
+    let cos_azimuth =
+        (declination.sin() - sin_altitude * latitude.sin()) / (altitude.cos() * latitude.cos());
+    let mut azimuth = cos_azimuth.clamp(-1., 1.).acos();
+    if hour_angle > 0. {
+        azimuth = 2. * PI - azimuth;
+    }
+
+    Vec3::new(
+        altitude.cos() * azimuth.sin(),
+        altitude.sin(),
+        -altitude.cos() * azimuth.cos(),
+    )
+}
+
+// A distant disk of constant radiance, sampled by the solid angle it subtends.
+#[derive(Clone, Copy)]
+pub struct SunLight {
+    uvw: Onb,
+    radiance: Color,
+    cos_max: f64,
+    sin_max: f64,
+}
+
+impl SunLight {
+    pub fn new(direction: Vec3, radiance: Color, angular_diameter: f64) -> Self {
+        let half = (angular_diameter / 2.).to_radians();
+        Self {
+            uvw: Onb::build_from_w(direction),
+            radiance,
+            cos_max: half.cos(),
+            sin_max: half.sin(),
+        }
+    }
+}
+
+impl Hittable for SunLight {
+    fn hit(&self, _r: Ray, _t_min: f64, _t_max: f64) -> Option<HitRecord> {
+        None
+    }
+
+    fn bounding_box(&self, _time0: f64, _time1: f64) -> Option<AABB> {
+        None
+    }
+
+    fn pdf_value(&self, _o: Point3, v: Vec3) -> f64 {
+        if Vec3::dot(v.to_unit(), self.uvw.w()) >= self.cos_max {
+            1. / (2. * PI * (1. - self.cos_max))
+        } else {
+            0.
+        }
+    }
+
+    fn random(&self, _o: Point3) -> Vec3 {
+        self.uvw.local_vec(random_to_sphere(self.sin_max, 1.))
+    }
+
+    fn environment(&self, direction: Vec3) -> Color {
+        if Vec3::dot(direction.to_unit(), self.uvw.w()) >= self.cos_max {
+            self.radiance
+        } else {
+            Color::new(0., 0., 0.)
+        }
+    }
+
+    fn power(&self) -> f64 {
+        PI * self.radiance.luminance() * 2. * PI * (1. - self.cos_max)
+    }
+}
@@ -1,5 +1,8 @@
 mod basic;
+mod bench;
 mod hittable;
+mod import;
+mod light;
 mod material;
 mod scene;
 mod texture;
@@ -23,9 +26,18 @@ use basic::{
     vec::{Color, Point3, Vec3},
 };
 use hittable::bvh::BvhNode;
-use hittable::{Hittable, HittableList};
+use hittable::{HitRecord, Hittable};
+use light::{sampler::LightSampler, LightList};
 
 fn main() {
+    // `cargo run --release -- bench` compares the BVH builders and the scalar and
+    // SIMD mesh traversals instead of rendering
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench::bvh();
+        bench::mesh();
+        return;
+    }
+
     print!("{}[2J", 27 as char); // Clear screen
     print!("{esc}[2J{esc}[1;1H", esc = 27 as char); // Set cursor position as 1,1
 
@@ -86,8 +98,18 @@ fn main() {
     );
 
     // World & lights
-    let (main_world, main_lights) = scene::wwscene();
-    let main_world = BvhNode::new_list(&main_world, time0, time1);
+    let (main_world, main_lights, main_delta_lights) = scene::wwscene();
+    let main_world = match BvhNode::new_list(&main_world, time0, time1) {
+        Ok(world) => world,
+        Err(e) => {
+            println!("{}", style(format!("Building BVH fails: {}", e)).red());
+            exit(1);
+        }
+    };
+    let scene_radius = main_world.bounded_box().map_or(1., |world_box| {
+        (world_box.max - world_box.min).length() / 2.
+    });
+    let main_lights = LightSampler::new(&main_lights, scene_radius);
 
     // Random line
     let mut random_line_id: [u32; IMAGE_HEIGHT as usize] = [0; IMAGE_HEIGHT as usize];
@@ -118,6 +140,7 @@ fn main() {
         // world & lights
         let world = main_world.clone();
         let lights = main_lights.clone();
+        let delta_lights = main_delta_lights.clone();
 
         //progress
         let mp = multiprogress.clone();
@@ -147,7 +170,8 @@ fn main() {
                             let u = (x as f64 + rand_u) / (IMAGE_WIDTH - 1) as f64;
                             let v = (y as f64 + rand_v) / (IMAGE_HEIGHT - 1) as f64;
                             let r = cam.get_ray(u, v);
-                            pixel_color += ray_color(r, background, &world, &lights, MAX_DEPTH);
+                            pixel_color +=
+                                ray_color(r, background, &world, &lights, &delta_lights, MAX_DEPTH);
                         }
                         section_pixel_color.push(pixel_color);
                     }
@@ -234,7 +258,8 @@ fn ray_color(
     r: Ray,
     background: Color,
     world: &BvhNode,
-    lights: &HittableList,
+    lights: &LightSampler,
+    delta_lights: &LightList,
     depth: i32,
 ) -> Color {
     if depth <= 0 {
@@ -244,8 +269,17 @@ fn ray_color(
         let emitted = rec.mat_ptr.emitted(r, &rec, rec.u, rec.v, rec.p);
         if let Some(srec) = rec.mat_ptr.scatter(r, &rec) {
             if let Some(specular) = srec.specular_ray {
+                // Materials scatter from `rec.p`, which may be slightly off the surface
+                let specular = rec.spawn_ray(specular.dir, specular.tm);
                 return srec.attenuation
-                    * ray_color(specular, background, &world, lights, depth - 1);
+                    * ray_color(
+                        specular,
+                        background,
+                        &world,
+                        lights,
+                        delta_lights,
+                        depth - 1,
+                    );
             }
 
             // if srec.pdf_ptr.is_none() {
@@ -260,21 +294,64 @@ fn ray_color(
             //             );
             // }
 
-            let light_ptr = HittablePdf::new(lights, rec.p);
-            let p = MixturePdf::new(light_ptr, srec.pdf_ptr.unwrap());
-            let scattered = Ray::new(rec.p, p.generate(), r.tm);
-            let pdf_val = p.value(scattered.dir);
+            let direct = direct_light(r, &rec, srec.attenuation, world, delta_lights);
+
+            let cos_pdf = srec.pdf_ptr.unwrap();
+            let (scattered, pdf_val) = if lights.is_empty() {
+                let scattered = rec.spawn_ray(cos_pdf.generate(), r.tm);
+                (scattered, cos_pdf.value(scattered.dir))
+            } else {
+                let light_ptr = HittablePdf::new(lights, rec.p);
+                let p = MixturePdf::new(light_ptr, cos_pdf);
+                let scattered = rec.spawn_ray(p.generate(), r.tm);
+                (scattered, p.value(scattered.dir))
+            };
+            // Directions below the actual surface would leak through it
+            if Vec3::dot(scattered.dir, rec.geometric_normal) <= 0. {
+                return emitted + direct;
+            }
             emitted
+                + direct
                 + srec.attenuation
                     * rec.mat_ptr.scattering_pdf(r, &rec, scattered)
-                    * ray_color(scattered, background, &world, lights, depth - 1)
+                    * ray_color(
+                        scattered,
+                        background,
+                        &world,
+                        lights,
+                        delta_lights,
+                        depth - 1,
+                    )
                     / pdf_val
         } else {
             emitted
         }
     } else {
-        background
+        background + lights.environment(r.dir)
+    }
+}
+
+// Next event estimation for the lights which cannot be hit by scattered rays.
+fn direct_light(
+    r: Ray,
+    rec: &HitRecord,
+    attenuation: Color,
+    world: &BvhNode,
+    delta_lights: &LightList,
+) -> Color {
+    let mut direct = Color::new(0., 0., 0.);
+    for light in &delta_lights.objects {
+        if let Some(sample) = light.sample_li(rec.p) {
+            let shadow_ray = rec.spawn_ray(sample.dir, r.tm);
+            if Vec3::dot(sample.dir, rec.geometric_normal) <= 0. {
+                continue;
+            }
+            if world.hit(shadow_ray, 0.001, sample.dis).is_none() {
+                direct += attenuation * rec.mat_ptr.scattering_pdf(r, rec, shadow_ray) * sample.li;
+            }
+        }
     }
+    direct
 }
 
 fn write_color(pixel_color: Color, samples_per_pixel: i32) -> [u8; 3] {
@@ -1,10 +1,12 @@
 use std::f64::consts::PI;
+use std::sync::Arc;
 
 use rand::Rng;
 
 use crate::{
     basic::ray::Ray,
     basic::{
+        onb::Onb,
         pdf::CosPdf,
         vec::{random_in_unit_sphere, reflect, refract, Color, Point3, Vec3},
     },
@@ -22,6 +24,25 @@ pub trait Material: Send + Sync {
     fn emitted(&self, _r_in: Ray, _rec: &HitRecord, _u: f64, _v: f64, _p: Point3) -> Color {
         Color::new(0., 0., 0.)
     }
+    // Emitted power per unit area, used to pick lights by power.
+    fn radiant_exitance(&self) -> f64 {
+        0.
+    }
+}
+
+impl Material for Arc<dyn Material> {
+    fn scatter(&self, r_in: Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        self.as_ref().scatter(r_in, rec)
+    }
+    fn scattering_pdf(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> f64 {
+        self.as_ref().scattering_pdf(r_in, rec, scattered)
+    }
+    fn emitted(&self, r_in: Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        self.as_ref().emitted(r_in, rec, u, v, p)
+    }
+    fn radiant_exitance(&self) -> f64 {
+        self.as_ref().radiant_exitance()
+    }
 }
 
 #[derive(Clone)]
@@ -51,7 +72,7 @@ impl<T: Texture + Clone> Material for Lambertian<T> {
     fn scatter(&self, _r_in: Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         Some(ScatterRecord::new(
             None,
-            self.albedo.value(rec.u, rec.v, rec.p),
+            self.albedo.value_at(rec),
             Some(CosPdf::new(rec.normal)),
         ))
     }
@@ -153,33 +174,80 @@ where
     T: Texture + Clone,
 {
     emit: T,
+    power: f64,
+    two_sided: bool,
+    falloff: f64,
 }
 
 impl<T: Texture + Clone> DiffuseLight<T> {
     #[allow(dead_code)]
     pub fn new_arc(emit: T) -> Self {
-        Self { emit }
+        Self::new_power(emit, 1.)
+    }
+
+    // `emit` only gives the color of the light, `power` scales its radiance.
+    pub fn new_power(emit: T, power: f64) -> Self {
+        Self {
+            emit,
+            power,
+            two_sided: false,
+            falloff: 0.,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn two_sided(mut self) -> Self {
+        self.two_sided = true;
+        self
+    }
+
+    // Radiance is scaled by cos^exponent of the angle to the normal.
+    #[allow(dead_code)]
+    pub fn falloff(mut self, exponent: f64) -> Self {
+        self.falloff = exponent;
+        self
     }
 }
 
 impl DiffuseLight<SolidColor> {
     pub fn new(c: Color) -> Self {
-        Self {
-            emit: SolidColor::new(c),
-        }
+        Self::new_power(SolidColor::new(c), 1.)
     }
 }
 
 impl<T: Texture + Clone> Material for DiffuseLight<T> {
-    fn emitted(&self, _r_in: Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
-        if rec.front_face {
-            self.emit.value(u, v, p)
-        } else {
-            Color::new(0., 0., 0.)
+    fn emitted(&self, r_in: Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        if !rec.front_face && !self.two_sided {
+            return Color::new(0., 0., 0.);
+        }
+        let mut strength = self.power;
+        if self.falloff > 0. {
+            let cos = Vec3::dot(rec.normal, -r_in.dir.to_unit()).max(0.);
+            strength *= cos.powf(self.falloff);
         }
+        self.emit.value(u, v, p) * strength
+    }
+    fn radiant_exitance(&self) -> f64 {
+        // Integrate the cosine lobe over the average of the texture
+        let sides = if self.two_sided { 2. } else { 1. };
+        average_luminance(&self.emit) * self.power * 2. * PI / (self.falloff + 2.) * sides
     }
 }
 
+// Luminance of a texture averaged over a few points.
+fn average_luminance<T: Texture>(texture: &T) -> f64 {
+    let n = 4;
+    let mut sum = 0.;
+    for i in 0..n {
+        for j in 0..n {
+            let u = (i as f64 + 0.5) / n as f64;
+            let v = (j as f64 + 0.5) / n as f64;
+            sum += texture.value(u, v, Point3::new(0., 0., 0.)).luminance();
+        }
+    }
+    sum / (n * n) as f64
+}
+
 #[derive(Clone, Copy)]
 pub struct Isotropic<T>
 where
@@ -207,12 +275,300 @@ impl<T: Texture + Clone> Material for Isotropic<T> {
     fn scatter(&self, r_in: Ray, rec: &HitRecord) -> Option<ScatterRecord> {
         Some(ScatterRecord::new(
             Some(Ray::new(rec.p, random_in_unit_sphere(), r_in.tm)),
-            self.albedo.value(rec.u, rec.v, rec.p),
+            self.albedo.value_at(rec),
+            None,
+        ))
+    }
+}
+
+// glTF metallic-roughness material. The metallic value is read from the blue channel
+// of `metallic_roughness` and the roughness from its green one, times the factors.
+#[derive(Clone)]
+pub struct Pbr<B, R, E>
+where
+    B: Texture + Clone,
+    R: Texture + Clone,
+    E: Texture + Clone,
+{
+    base_color: B,
+    base_color_factor: Color,
+    metallic_roughness: R,
+    metallic: f64,
+    roughness: f64,
+    emissive: E,
+    emissive_factor: Color,
+}
+
+impl<B: Texture + Clone, R: Texture + Clone, E: Texture + Clone> Pbr<B, R, E> {
+    pub fn new(
+        base_color: B,
+        base_color_factor: Color,
+        metallic_roughness: R,
+        metallic: f64,
+        roughness: f64,
+        emissive: E,
+        emissive_factor: Color,
+    ) -> Self {
+        Self {
+            base_color,
+            base_color_factor,
+            metallic_roughness,
+            metallic,
+            roughness,
+            emissive,
+            emissive_factor,
+        }
+    }
+}
+
+impl<B: Texture + Clone, R: Texture + Clone, E: Texture + Clone> Material for Pbr<B, R, E> {
+    fn scatter(&self, r_in: Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let base = self.base_color.value_at(rec) * self.base_color_factor;
+        let mr = self.metallic_roughness.value_at(rec);
+        let metallic = (self.metallic * mr.z).clamp(0., 1.);
+        let roughness = (self.roughness * mr.y).clamp(0., 1.);
+
+        // Schlick's approximation, then pick one of the two lobes by their weights
+        let unit_direction = r_in.dir.to_unit();
+        let cos = Vec3::dot(-unit_direction, rec.normal).clamp(0., 1.);
+        let white = Color::new(1., 1., 1.);
+        let f0 = Color::new(0.04, 0.04, 0.04) * (1. - metallic) + base * metallic;
+        let fresnel = f0 + (white - f0) * (1. - cos).powi(5);
+        let diffuse = base * (1. - metallic) * (white - fresnel);
+
+        let total = fresnel.luminance() + diffuse.luminance();
+        if total <= 0. {
+            return None;
+        }
+        let specular_prob = fresnel.luminance() / total;
+        let mut rng = rand::thread_rng();
+        if rng.gen::<f64>() < specular_prob {
+            let reflected = reflect(unit_direction, rec.normal);
+            Some(ScatterRecord::new(
+                Some(Ray::new(
+                    rec.p,
+                    reflected + random_in_unit_sphere() * roughness * roughness,
+                    r_in.tm,
+                )),
+                fresnel / specular_prob,
+                None,
+            ))
+        } else {
+            Some(ScatterRecord::new(
+                None,
+                diffuse / (1. - specular_prob),
+                Some(CosPdf::new(rec.normal)),
+            ))
+        }
+    }
+    fn scattering_pdf(&self, _r_in: Ray, rec: &HitRecord, scattered: Ray) -> f64 {
+        let cosine = Vec3::dot(rec.normal, scattered.dir.to_unit());
+        if cosine < 0. {
+            0.
+        } else {
+            cosine / PI
+        }
+    }
+    fn emitted(&self, _r_in: Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        if !rec.front_face {
+            return Color::new(0., 0., 0.);
+        }
+        self.emissive.value(u, v, p) * self.emissive_factor
+    }
+    fn radiant_exitance(&self) -> f64 {
+        average_luminance(&self.emissive) * self.emissive_factor.luminance() * PI
+    }
+}
+
+const BUMP_DELTA: f64 = 0.0005;
+
+// Perturbs the shading normal of another material by the slope of a height map,
+// whose values are scaled by `scale` into world units.
+#[derive(Clone)]
+pub struct BumpMap<M, T>
+where
+    M: Material,
+    T: Texture + Clone,
+{
+    inner: M,
+    height: T,
+    scale: f64,
+}
+
+impl<M: Material, T: Texture + Clone> BumpMap<M, T> {
+    pub fn new(inner: M, height: T, scale: f64) -> Self {
+        Self {
+            inner,
+            height,
+            scale,
+        }
+    }
+
+    fn bump<'a>(&self, rec: &HitRecord<'a>) -> HitRecord<'a> {
+        let height = |u: f64, v: f64| self.height.value(u, v, rec.p).luminance();
+        let h = height(rec.u, rec.v);
+        let dhdu = (height(rec.u + BUMP_DELTA, rec.v) - h) / BUMP_DELTA;
+        let dhdv = (height(rec.u, rec.v + BUMP_DELTA) - h) / BUMP_DELTA;
+
+        // Slopes in world units, taking the texture to be about as long in v as in u
+        let n = rec.normal;
+        let tangent = rec.dpdu - n * Vec3::dot(rec.dpdu, n);
+        let (tangent, length) = if tangent.length_sqr() > 0. {
+            (tangent.to_unit(), tangent.length())
+        } else {
+            (Onb::build_from_w(n).u(), 1.)
+        };
+        let bitangent = Vec3::cross(n, tangent);
+
+        let mut bumped = *rec;
+        bumped.normal = (n - (tangent * dhdu + bitangent * dhdv) * (self.scale / length)).to_unit();
+        bumped
+    }
+}
+
+impl<M: Material, T: Texture + Clone> Material for BumpMap<M, T> {
+    fn scatter(&self, r_in: Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        self.inner.scatter(r_in, &self.bump(rec))
+    }
+    fn scattering_pdf(&self, r_in: Ray, rec: &HitRecord, scattered: Ray) -> f64 {
+        self.inner.scattering_pdf(r_in, &self.bump(rec), scattered)
+    }
+    fn emitted(&self, r_in: Ray, rec: &HitRecord, u: f64, v: f64, p: Point3) -> Color {
+        self.inner.emitted(r_in, rec, u, v, p)
+    }
+    fn radiant_exitance(&self) -> f64 {
+        self.inner.radiant_exitance()
+    }
+}
+
+const HAIR_ETA: f64 = 1.55;
+
+// Hair fibers after a simplified Marschner model, with a reflection off the
+// surface (R), a transmission through the fiber (TT) and a reflection inside it
+// (TRT). Meant for `Curve`s, which give the direction of the fiber in `dpdu` and
+// the offset across it in `v`. Like `Metal`, it picks up light only through the
+// rays it scatters.
+#[derive(Clone, Copy)]
+pub struct Hair {
+    sigma_a: Color,
+    // Longitudinal and azimuthal roughness, from 0 to 1.
+    beta_m: f64,
+    beta_n: f64,
+    // Tilt of the cuticle scales, in radians.
+    alpha: f64,
+}
+
+impl Hair {
+    // `sigma_a` is the absorption per diameter crossed, `alpha` is in degrees.
+    #[allow(dead_code)]
+    pub fn new(sigma_a: Color, beta_m: f64, beta_n: f64, alpha: f64) -> Self {
+        Self {
+            sigma_a,
+            beta_m,
+            beta_n,
+            alpha: alpha.to_radians(),
+        }
+    }
+
+    // Absorption from the concentration of the dark and the red pigment. 0 is
+    // white, about 1.3 blond, 8 black.
+    pub fn from_melanin(eumelanin: f64, pheomelanin: f64, beta_m: f64, beta_n: f64) -> Self {
+        let sigma_a =
+            Color::new(0.419, 0.697, 1.37) * eumelanin + Color::new(0.187, 0.4, 1.05) * pheomelanin;
+        Self::new(sigma_a, beta_m, beta_n, 2.)
+    }
+}
+
+impl Material for Hair {
+    #[allow(clippy::many_single_char_names)]
+    fn scatter(&self, r_in: Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let wo = -r_in.dir.to_unit();
+        let tangent = if rec.dpdu.length_sqr() > 0. {
+            rec.dpdu.to_unit()
+        } else {
+            Onb::build_from_w(rec.normal).u()
+        };
+        let sin_theta_o = Vec3::dot(wo, tangent).clamp(-1., 1.);
+        let cos_theta_o = (1. - sin_theta_o * sin_theta_o).sqrt();
+        let perp = wo - tangent * sin_theta_o;
+        let n = if perp.length_sqr() > 0. {
+            perp.to_unit()
+        } else {
+            rec.normal
+        };
+        let b = Vec3::cross(tangent, n);
+
+        // Angles of the path through the fiber
+        let h = (2. * rec.v - 1.).clamp(-1., 1.);
+        let gamma_o = h.asin();
+        let eta_p =
+            (HAIR_ETA * HAIR_ETA - sin_theta_o * sin_theta_o).sqrt() / cos_theta_o.max(1e-8);
+        let sin_theta_t = sin_theta_o / HAIR_ETA;
+        let cos_theta_t = (1. - sin_theta_t * sin_theta_t).sqrt();
+        let gamma_t = (h / eta_p).asin();
+        let length = 2. * gamma_t.cos() / cos_theta_t;
+        let transmittance = Color::new(
+            (-self.sigma_a.x * length).exp(),
+            (-self.sigma_a.y * length).exp(),
+            (-self.sigma_a.z * length).exp(),
+        );
+        let fresnel = Dielectric::reflectance(cos_theta_o * gamma_o.cos(), HAIR_ETA);
+
+        let white = Color::new(1., 1., 1.);
+        let lobes = [
+            white * fresnel,
+            transmittance * (1. - fresnel).powi(2),
+            transmittance * transmittance * (1. - fresnel).powi(2) * fresnel,
+        ];
+        let total: f64 = lobes.iter().map(|a| a.luminance()).sum();
+        if total <= 0. {
+            return None;
+        }
+        let mut rng = rand::thread_rng();
+        let mut choice = rng.gen::<f64>() * total;
+        let mut p = 0;
+        while p < 2 && choice >= lobes[p].luminance() {
+            choice -= lobes[p].luminance();
+            p += 1;
+        }
+        let prob = lobes[p].luminance() / total;
+
+        // Longitudinal angle: a Gaussian around the mirror direction, shifted by
+        // the scales and wider for the lobes that go through the fiber
+        let shift = [-2. * self.alpha, self.alpha, 4. * self.alpha][p];
+        let width = [self.beta_m, 0.5 * self.beta_m, 2. * self.beta_m][p];
+        let gaussian =
+            (-2. * (1. - rng.gen::<f64>()).ln()).sqrt() * (2. * PI * rng.gen::<f64>()).cos();
+        let mut theta_i = -(sin_theta_o.asin() + shift) + width * gaussian;
+        if theta_i > PI / 2. {
+            theta_i = PI - theta_i;
+        } else if theta_i < -PI / 2. {
+            theta_i = -PI - theta_i;
+        }
+
+        // Azimuthal angle: a logistic around the exit of the p-th path
+        let s = (PI / 8.).sqrt()
+            * (0.265 * self.beta_n + 1.194 * self.beta_n.powi(2) + 5.372 * self.beta_n.powi(22));
+        let p_f = p as f64;
+        let phi =
+            2. * p_f * gamma_t - 2. * gamma_o + p_f * PI + sample_trimmed_logistic(rng.gen(), s);
+
+        let direction = tangent * theta_i.sin() + (n * phi.cos() + b * phi.sin()) * theta_i.cos();
+        Some(ScatterRecord::new(
+            Some(Ray::new(rec.p, direction, r_in.tm)),
+            lobes[p] / prob,
             None,
         ))
     }
 }
 
+// Logistic distribution of scale `s`, restricted to [-pi, pi].
+fn sample_trimmed_logistic(x: f64, s: f64) -> f64 {
+    let cdf = |t: f64| 1. / (1. + (-t / s).exp());
+    let k = cdf(PI) - cdf(-PI);
+    (-s * (1. / (x * k + cdf(-PI)) - 1.).ln()).clamp(-PI, PI)
+}
+
 #[derive(Clone, Copy)]
 pub struct ScatterRecord {
     pub specular_ray: Option<Ray>,
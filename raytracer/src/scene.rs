@@ -1,21 +1,42 @@
+use std::f64::consts::PI;
 use std::sync::Arc;
 
+use console::style;
 use rand::Rng;
 
 use crate::{
-    basic::vec::{random_in_unit_xz_disk, Color, Point3, Vec3},
+    basic::{
+        camera::Camera,
+        transform::{AnimatedTransform, Keyframe, Quaternion, Transform},
+        vec::{random_in_unit_xz_disk, Color, Point3, Vec3},
+    },
     hittable::{
         aarect::{XYRect, XZRect, YZRect},
         boxes::Boxes,
-        bvh::BvhNode,
+        bvh::{aabb::AABB, BvhNode},
         constantmedium::ConstantMedium,
+        csg::Csg,
+        curve::{Curve, CurveMode},
+        heightfield::Heightfield,
+        instance::{AnimatedInstance, Instance},
+        mesh::TriangleMesh,
+        quadric::{Cone, Cylinder, Disk, Paraboloid, Plane},
         ring::Ring,
+        sdf::{Mandelbulb, Repeat, SdfBox, SdfObject, SdfSphere, SdfTorus, SmoothUnion, Twist},
         sphere::{MovingSphere, Sphere},
+        torus::Torus,
         triangle::Triangle,
-        FlipFace, HittableList, RotateY, Translate, Zoom,
+        FlipFace, Hittable, HittableList, RotateY, Translate,
+    },
+    import::{gltf::load_gltf, obj::load_obj, ply::load_ply, stl::load_stl, ImportError},
+    light::{
+        delta::{DirectionalLight, PointLight, SpotLight},
+        environment::EnvironmentLight,
+        sky::Sky,
+        LightList,
     },
-    material::{Dielectric, DiffuseLight, Lambertian, Metal},
-    texture::{CheckerTexture, ImageTexture, NoiseTexture},
+    material::{Dielectric, DiffuseLight, Hair, Lambertian, Material, Metal},
+    texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, VertexColor},
 };
 
 #[allow(dead_code)]
@@ -162,7 +183,521 @@ pub fn simple_light() -> HittableList {
 }
 
 #[allow(dead_code)]
-pub fn cornell_box() -> (HittableList, HittableList) {
+pub fn delta_lights() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+    let pertext = NoiseTexture::new(4.);
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Lambertian::new_arc(pertext),
+    )));
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., 2., 0.),
+        2.,
+        Lambertian::new_arc(pertext),
+    )));
+
+    let mut delta_lights = LightList::default();
+    delta_lights.add(Arc::new(PointLight::new(
+        Point3::new(4., 5., 4.),
+        Color::new(20., 20., 20.),
+    )));
+    delta_lights.add(Arc::new(SpotLight::new(
+        Point3::new(-6., 8., 2.),
+        Point3::new(0., 2., 0.),
+        Color::new(150., 120., 90.),
+        10.,
+        20.,
+    )));
+    delta_lights.add(Arc::new(DirectionalLight::new(
+        Vec3::new(1., 2., -1.),
+        Color::new(0.3, 0.3, 0.35),
+        0.53,
+    )));
+
+    (world, HittableList::default(), delta_lights)
+}
+
+#[allow(dead_code)]
+pub fn environment_map() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-2.2, 1., 0.),
+        1.,
+        Lambertian::new(Color::new(0.4, 0.2, 0.1)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., 1., 0.),
+        1.,
+        Dielectric::new(1.5),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(2.2, 1., 0.),
+        1.,
+        Metal::new(Color::new(0.7, 0.6, 0.5), 0.05),
+    )));
+
+    let mut lights = HittableList::default();
+    match EnvironmentLight::new("source/studio.hdr", 0., 1.) {
+        Ok(light) => lights.add(Arc::new(light)),
+        Err(e) => println!(
+            "{}",
+            style(format!("Loading environment fails: {}", e)).red()
+        ),
+    }
+
+    (world, lights, LightList::default())
+}
+
+#[allow(dead_code)]
+pub fn daylight() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Lambertian::new(Color::new(0.4, 0.35, 0.3)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-2.2, 1., 0.),
+        1.,
+        Lambertian::new(Color::new(0.8, 0.8, 0.8)),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(2.2, 1., 0.),
+        1.,
+        Metal::new(Color::new(0.7, 0.6, 0.5), 0.2),
+    )));
+
+    let sky = Sky::new_time(172, 16.5, 31.2, 3., Color::new(0.3, 0.3, 0.3), 0.05);
+    let mut lights = HittableList::default();
+    lights.add(Arc::new(sky.sky_light(512, 256)));
+    lights.add(Arc::new(sky.sun_light()));
+
+    (world, lights, LightList::default())
+}
+
+// A glTF model under the studio environment, seen from the first camera of the file.
+#[allow(dead_code)]
+pub fn gltf_model(aspect_ratio: f64) -> (HittableList, HittableList, LightList, Option<Camera>) {
+    let mut lights = HittableList::default();
+    match EnvironmentLight::new("source/studio.hdr", 0., 1.) {
+        Ok(light) => lights.add(Arc::new(light)),
+        Err(e) => println!(
+            "{}",
+            style(format!("Loading environment fails: {}", e)).red()
+        ),
+    }
+
+    match load_gltf("source/gltf/scene.glb") {
+        Ok(scene) => {
+            let camera = scene
+                .cameras
+                .first()
+                .map(|c| c.camera(aspect_ratio, 0., 1.));
+            (scene.world, lights, LightList::default(), camera)
+        }
+        Err(e) => {
+            println!("{}", style(format!("Loading glTF fails: {}", e)).red());
+            (HittableList::default(), lights, LightList::default(), None)
+        }
+    }
+}
+
+// A scanned PLY model in its vertex colors and a metal STL part, under the daylight sky.
+#[allow(dead_code)]
+pub fn scanned_models() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Lambertian::new(Color::new(0.4, 0.35, 0.3)),
+    )));
+    match load_ply("source/ply/scan.ply") {
+        Ok(data) => world.add(Arc::new(TriangleMesh::new(
+            Arc::new(data),
+            Lambertian::new_arc(VertexColor),
+        ))),
+        Err(e) => println!("{}", style(format!("Loading scan fails: {}", e)).red()),
+    }
+    match load_stl("source/stl/part.stl") {
+        Ok(data) => world.add(Arc::new(Translate::new(
+            TriangleMesh::new(Arc::new(data), Metal::new(Color::new(0.7, 0.7, 0.75), 0.1)),
+            Vec3::new(3., 0., 0.),
+        ))),
+        Err(e) => println!("{}", style(format!("Loading part fails: {}", e)).red()),
+    }
+
+    let sky = Sky::new_time(172, 16.5, 31.2, 3., Color::new(0.3, 0.3, 0.3), 0.05);
+    let mut lights = HittableList::default();
+    lights.add(Arc::new(sky.sky_light(512, 256)));
+    lights.add(Arc::new(sky.sun_light()));
+
+    (world, lights, LightList::default())
+}
+
+// A box spinning and sliding, and a sphere growing, during the shutter.
+#[allow(dead_code)]
+pub fn motion_blur() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Lambertian::new(Color::new(0.4, 0.35, 0.3)),
+    )));
+
+    let up = Vec3::new(0., 1., 0.);
+    let cube = Boxes::new(
+        Point3::new(-0.5, -0.5, -0.5),
+        Point3::new(0.5, 0.5, 0.5),
+        Lambertian::new(Color::new(0.8, 0.3, 0.2)),
+    );
+    let spin = AnimatedTransform::new(vec![
+        Keyframe::new(
+            0.,
+            Vec3::new(-2.5, 1., 0.),
+            Quaternion::identity(),
+            Vec3::new(1., 1., 1.),
+        ),
+        Keyframe::new(
+            0.5,
+            Vec3::new(-2., 1.2, 0.),
+            Quaternion::from_axis_angle(up, 45.),
+            Vec3::new(1., 1., 1.),
+        ),
+        Keyframe::new(
+            1.,
+            Vec3::new(-1.5, 1., 0.),
+            Quaternion::from_axis_angle(up, 90.),
+            Vec3::new(1., 1., 1.),
+        ),
+    ]);
+    world.add(Arc::new(AnimatedInstance::new(cube, spin)));
+
+    let ball = Sphere::new(
+        Point3::new(0., 0., 0.),
+        1.,
+        Metal::new(Color::new(0.7, 0.6, 0.5), 0.1),
+    );
+    let grow = AnimatedTransform::new(vec![
+        Keyframe::new(
+            0.,
+            Vec3::new(2., 0.6, 0.),
+            Quaternion::identity(),
+            Vec3::new(0.6, 0.6, 0.6),
+        ),
+        Keyframe::new(
+            1.,
+            Vec3::new(2., 1., 0.),
+            Quaternion::identity(),
+            Vec3::new(1., 1., 1.),
+        ),
+    ]);
+    world.add(Arc::new(AnimatedInstance::new(ball, grow)));
+
+    let sky = Sky::new_time(172, 16.5, 31.2, 3., Color::new(0.3, 0.3, 0.3), 0.05);
+    let mut lights = HittableList::default();
+    lights.add(Arc::new(sky.sky_light(512, 256)));
+    lights.add(Arc::new(sky.sun_light()));
+
+    (world, lights, LightList::default())
+}
+
+// The analytic shapes, lit by a tilted disk and a glowing ring.
+#[allow(dead_code)]
+pub fn quadrics() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+
+    world.add(Arc::new(Disk::new(
+        Point3::new(0., 0., 0.),
+        Vec3::new(0., 1., 0.),
+        20.,
+        Lambertian::new(Color::new(0.4, 0.35, 0.3)),
+    )));
+    world.add(Arc::new(
+        Cylinder::new(
+            Point3::new(-3., 0., 0.),
+            Point3::new(-3., 2., 0.),
+            0.7,
+            Lambertian::new(Color::new(0.8, 0.3, 0.2)),
+        )
+        .capped(),
+    ));
+    world.add(Arc::new(
+        Cone::new(
+            Point3::new(-1., 0., 1.),
+            Point3::new(-1., 2., 1.),
+            0.8,
+            Lambertian::new(Color::new(0.2, 0.5, 0.8)),
+        )
+        .capped(),
+    ));
+    world.add(Arc::new(Torus::new(
+        Point3::new(1., 0.9, 0.),
+        Vec3::new(0., 1., 1.),
+        0.7,
+        0.25,
+        Metal::new(Color::new(0.8, 0.7, 0.4), 0.05),
+    )));
+    world.add(Arc::new(Paraboloid::new(
+        Point3::new(3., 0., 0.),
+        Point3::new(3., 1.5, 0.),
+        1.,
+        Metal::new(Color::new(0.7, 0.7, 0.7), 0.2),
+    )));
+
+    let mut lights = HittableList::default();
+    let disk_light = Arc::new(Disk::new(
+        Point3::new(0., 6., -3.),
+        Vec3::new(0., -1., 0.5),
+        1.5,
+        DiffuseLight::new(Color::new(8., 8., 8.)),
+    ));
+    world.add(disk_light.clone());
+    lights.add(disk_light);
+    let ring_light = Arc::new(Disk::annulus(
+        Point3::new(0., 3., 4.),
+        Vec3::new(0., 0., -1.),
+        2.,
+        2.2,
+        DiffuseLight::new(Color::new(6., 3., 1.)),
+    ));
+    world.add(ring_light.clone());
+    lights.add(ring_light);
+
+    (world, lights, LightList::default())
+}
+
+// A biconvex glass lens, a hollow glass sphere and a box with a spherical bite,
+// under the daylight sky.
+#[allow(dead_code)]
+pub fn csg() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Lambertian::new(Color::new(0.4, 0.35, 0.3)),
+    )));
+
+    let glass = Dielectric::new(1.5);
+    world.add(Arc::new(Csg::intersection(
+        Sphere::new(Point3::new(-2.5, 1.2, -1.6), 2., glass),
+        Sphere::new(Point3::new(-2.5, 1.2, 1.6), 2., glass),
+    )));
+    world.add(Arc::new(Csg::difference(
+        Sphere::new(Point3::new(0., 1., 0.), 1., glass),
+        Sphere::new(Point3::new(0., 1., 0.), 0.9, glass),
+    )));
+    world.add(Arc::new(Csg::difference(
+        Boxes::new(
+            Point3::new(1.8, 0., -0.8),
+            Point3::new(3.4, 1.6, 0.8),
+            Lambertian::new(Color::new(0.8, 0.3, 0.2)),
+        ),
+        Sphere::new(
+            Point3::new(3.4, 1.6, -0.8),
+            0.9,
+            Lambertian::new(Color::new(0.9, 0.9, 0.9)),
+        ),
+    )));
+
+    let sky = Sky::new_time(172, 16.5, 31.2, 3., Color::new(0.3, 0.3, 0.3), 0.05);
+    let mut lights = HittableList::default();
+    lights.add(Arc::new(sky.sky_light(512, 256)));
+    lights.add(Arc::new(sky.sun_light()));
+
+    (world, lights, LightList::default())
+}
+
+// Distance field shapes: a twisted rounded box, two blended spheres, a Mandelbulb
+// and a row of repeated rings, under the daylight sky.
+#[allow(dead_code)]
+pub fn sdf() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0., -1000., 0.),
+        1000.,
+        Lambertian::new(Color::new(0.4, 0.35, 0.3)),
+    )));
+
+    // Twisting turns about the y axis, so the box is built on it and moved afterwards
+    let twisted = Twist::new(
+        SdfBox::new(Point3::new(0., 1., 0.), Vec3::new(0.5, 1., 0.5), 0.1),
+        0.8,
+    );
+    world.add(Arc::new(Instance::new(
+        SdfObject::new(
+            twisted,
+            AABB::new(Point3::new(-0.75, 0., -0.75), Point3::new(0.75, 2., 0.75)),
+            Lambertian::new(Color::new(0.8, 0.3, 0.2)),
+        ),
+        Transform::translate(Vec3::new(-3., 0., 0.)),
+    )));
+
+    let blob = SmoothUnion::new(
+        SdfSphere::new(Point3::new(-0.3, 0.8, 0.), 0.6),
+        SdfSphere::new(Point3::new(0.4, 1., 0.), 0.5),
+        0.3,
+    );
+    world.add(Arc::new(SdfObject::new(
+        blob,
+        AABB::new(Point3::new(-1., 0., -0.7), Point3::new(1., 1.6, 0.7)),
+        Dielectric::new(1.5),
+    )));
+
+    // The bulb is built around the origin, so it is moved and scaled as a whole
+    let bulb = SdfObject::new(
+        Mandelbulb::new(8., 12),
+        AABB::new(Point3::new(-1.2, -1.2, -1.2), Point3::new(1.2, 1.2, 1.2)),
+        Metal::new(Color::new(0.8, 0.7, 0.4), 0.1),
+    )
+    .epsilon(1e-5)
+    .max_steps(512);
+    world.add(Arc::new(Instance::new(
+        bulb,
+        Transform::translate(Vec3::new(3., 1., 0.)) * Transform::scale(Vec3::new(0.8, 0.8, 0.8)),
+    )));
+
+    let row = Repeat::new(
+        SdfTorus::new(Point3::new(0., 0.1, 3.), 0.25, 0.1),
+        Vec3::new(0.8, 0., 0.),
+    );
+    world.add(Arc::new(SdfObject::new(
+        row,
+        AABB::new(Point3::new(-4., 0., 2.6), Point3::new(4., 0.2, 3.4)),
+        Lambertian::new(Color::new(0.2, 0.5, 0.8)),
+    )));
+
+    let sky = Sky::new_time(172, 16.5, 31.2, 3., Color::new(0.3, 0.3, 0.3), 0.05);
+    let mut lights = HittableList::default();
+    lights.add(Arc::new(sky.sky_light(512, 256)));
+    lights.add(Arc::new(sky.sun_light()));
+
+    (world, lights, LightList::default())
+}
+
+// Rolling hills made of a few sine waves under the daylight sky.
+#[allow(dead_code)]
+pub fn terrain() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+
+    let n = 256;
+    let mut heights = Vec::with_capacity(n * n);
+    for j in 0..n {
+        for i in 0..n {
+            let x = 2. * PI * i as f64 / (n - 1) as f64;
+            let z = 2. * PI * j as f64 / (n - 1) as f64;
+            heights.push(
+                30. + 18. * (1.5 * x).sin() * z.cos()
+                    + 9. * (3. * x + 2. * z).sin()
+                    + 3. * (9. * x).sin() * (7. * z).sin(),
+            );
+        }
+    }
+    world.add(Arc::new(
+        Heightfield::from_heights(
+            n,
+            n,
+            &heights,
+            Point3::new(0., 0., 0.),
+            400.,
+            400.,
+            Lambertian::new(Color::new(0.48, 0.83, 0.53)),
+        )
+        .unwrap(),
+    ));
+
+    let sky = Sky::new_time(172, 16.5, 31.2, 3., Color::new(0.3, 0.3, 0.3), 0.05);
+    let mut lights = HittableList::default();
+    lights.add(Arc::new(sky.sky_light(512, 256)));
+    lights.add(Arc::new(sky.sun_light()));
+
+    (world, lights, LightList::default())
+}
+
+// A furry ball of hair curves in a patch of grass blades, under the daylight sky.
+#[allow(dead_code)]
+pub fn fur() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+    let mut rng = rand::thread_rng();
+
+    world.add(Arc::new(Plane::new(
+        Point3::new(0., 0., 0.),
+        Vec3::new(0., 1., 0.),
+        Lambertian::new(Color::new(0.35, 0.25, 0.15)),
+    )));
+
+    let center = Point3::new(0., 1., 0.);
+    let radius = 0.7;
+    world.add(Arc::new(Sphere::new(
+        center,
+        radius,
+        Lambertian::new(Color::new(0.3, 0.2, 0.1)),
+    )));
+    let mut hairs = HittableList::default();
+    for _ in 0..20000 {
+        let normal = Vec3::random_unit_vector();
+        let bend = Vec3::random_unit_vector() * 0.05 - Vec3::new(0., 0.08, 0.);
+        let root = center + normal * radius;
+        hairs.add(Arc::new(Curve::new(
+            [
+                root,
+                root + normal * 0.1,
+                root + normal * 0.2 + bend,
+                root + normal * 0.3 + bend * 2.,
+            ],
+            0.006,
+            0.002,
+            CurveMode::Cylinder,
+            Hair::from_melanin(1.3, 0.3, 0.3, 0.3),
+        )));
+    }
+    world.add(Arc::new(BvhNode::new_list(&hairs, 0., 1.).unwrap()));
+
+    let mut grass = HittableList::default();
+    for _ in 0..5000 {
+        let root = Point3::new(rng.gen_range(-4.0..4.), 0., rng.gen_range(-4.0..4.));
+        if root.length() < 1. {
+            continue;
+        }
+        let height = rng.gen_range(0.3..0.6);
+        let lean = Vec3::new(rng.gen_range(-0.15..0.15), 0., rng.gen_range(-0.15..0.15));
+        grass.add(Arc::new(Curve::new(
+            [
+                root,
+                root + Vec3::new(0., height / 3., 0.),
+                root + Vec3::new(0., height * 2. / 3., 0.) + lean * 0.5,
+                root + Vec3::new(0., height, 0.) + lean * 2.,
+            ],
+            0.03,
+            0.,
+            CurveMode::Ribbon,
+            Lambertian::new(Color::new(0.2, 0.5, 0.1)),
+        )));
+    }
+    world.add(Arc::new(BvhNode::new_list(&grass, 0., 1.).unwrap()));
+
+    let sky = Sky::new_time(172, 16.5, 31.2, 3., Color::new(0.3, 0.3, 0.3), 0.05);
+    let mut lights = HittableList::default();
+    lights.add(Arc::new(sky.sky_light(512, 256)));
+    lights.add(Arc::new(sky.sun_light()));
+
+    (world, lights, LightList::default())
+}
+
+#[allow(dead_code)]
+pub fn cornell_box() -> (HittableList, HittableList, LightList) {
     let mut world: HittableList = Default::default();
 
     let light_strong = DiffuseLight::new(Color::new(60., 60., 60.));
@@ -192,7 +727,65 @@ pub fn cornell_box() -> (HittableList, HittableList) {
 
     lights.add(Arc::new(light_top));
 
-    (world, lights)
+    (world, lights, LightList::default())
+}
+
+#[allow(dead_code)]
+pub fn cornell_mesh_light() -> (HittableList, HittableList, LightList) {
+    let mut world: HittableList = Default::default();
+    let mut lights = HittableList::default();
+
+    let red = Lambertian::new(Color::new(0.65, 0.05, 0.05));
+    let white = Lambertian::new(Color::new(0.73, 0.73, 0.73));
+    let green = Lambertian::new(Color::new(0.12, 0.45, 0.15));
+
+    world.add(Arc::new(YZRect::new(0., 555., 0., 555., 555., red)));
+    world.add(Arc::new(YZRect::new(0., 555., 0., 555., 0., green)));
+    world.add(Arc::new(XZRect::new(0., 555., 0., 555., 0., white.clone())));
+    world.add(Arc::new(XZRect::new(
+        0.,
+        555.,
+        0.,
+        555.,
+        555.,
+        white.clone(),
+    )));
+    world.add(Arc::new(XYRect::new(
+        0.,
+        555.,
+        0.,
+        555.,
+        555.,
+        white.clone(),
+    )));
+
+    let box1 = Boxes::new(
+        Point3::new(0., 0., 0.),
+        Point3::new(165., 330., 165.),
+        white,
+    );
+    let box1 = RotateY::new(box1, 15.);
+    let box1 = Translate::new(box1, Vec3::new(265., 0., 295.));
+    world.add(Arc::new(box1));
+
+    // A hexagonal lamp made of triangles facing down, each one is sampled by its power
+    let light = DiffuseLight::new_power(SolidColor::new(Color::new(1., 0.9, 0.8)), 40.);
+    let center = Point3::new(278., 554., 279.5);
+    let sides = 6;
+    for i in 0..sides {
+        let theta0 = 2. * PI * i as f64 / sides as f64;
+        let theta1 = 2. * PI * (i + 1) as f64 / sides as f64;
+        let tri = Arc::new(Triangle::new(
+            center,
+            center + Vec3::new(theta0.cos(), 0., theta0.sin()) * 90.,
+            center + Vec3::new(theta1.cos(), 0., theta1.sin()) * 90.,
+            light.clone(),
+        ));
+        world.add(tri.clone());
+        lights.add(tri);
+    }
+
+    (world, lights, LightList::default())
 }
 
 #[allow(dead_code)]
@@ -258,32 +851,26 @@ pub fn cornell_smoke() -> HittableList {
 
 #[allow(dead_code)]
 pub fn final_scene() -> HittableList {
-    let mut box1: HittableList = Default::default();
-    let ground = Lambertian::new(Color::new(0.48, 0.83, 0.53));
-
-    let boxes_per_side = 20;
+    // A random height every 100 units, where the boxes of the book stood
+    let points_per_side = 21;
     let mut rng = rand::thread_rng();
-    for i in 0..boxes_per_side {
-        for j in 0..boxes_per_side {
-            let w = 100.;
-            let x0 = -1000. + i as f64 * w;
-            let z0 = -1000. + j as f64 * w;
-            let y0 = 0.;
-            let x1 = x0 + w;
-            let y1 = rng.gen_range(1.0..101.0);
-            let z1 = z0 + w;
-
-            box1.add(Arc::new(Boxes::new(
-                Point3::new(x0, y0, z0),
-                Point3::new(x1, y1, z1),
-                ground.clone(),
-            )));
-        }
-    }
+    let heights: Vec<f64> = (0..points_per_side * points_per_side)
+        .map(|_| rng.gen_range(1.0..101.0))
+        .collect();
+    let ground = Heightfield::from_heights(
+        points_per_side,
+        points_per_side,
+        &heights,
+        Point3::new(0., 0., 0.),
+        2000.,
+        2000.,
+        Lambertian::new(Color::new(0.48, 0.83, 0.53)),
+    )
+    .unwrap();
 
     let mut world: HittableList = Default::default();
 
-    world.add(Arc::new(BvhNode::new_list(&box1, 0., 1.)));
+    world.add(Arc::new(ground));
 
     let light = DiffuseLight::new(Color::new(7., 7., 7.));
     world.add(Arc::new(FlipFace::new(XZRect::new(
@@ -354,123 +941,62 @@ pub fn final_scene() -> HittableList {
     }
 
     world.add(Arc::new(Translate::new(
-        RotateY::new(BvhNode::new_list(&box2, 0., 1.), 15.),
+        RotateY::new(BvhNode::new_list(&box2, 0., 1.).unwrap(), 15.),
         Vec3::new(-100., 270., 395.),
     )));
 
     world
 }
 
-fn get_shuttle(world: &mut HittableList) {
-    let file_path = "source/obj/";
-    let file_name = file_path.to_string() + "Shuttle.obj";
-
-    let obj = tobj::load_obj(
-        file_name,
-        &tobj::LoadOptions {
-            single_index: true,
-            triangulate: true,
-            ..Default::default()
-        },
-    );
-
-    assert!(obj.is_ok());
-
-    let (models, _materials) = obj.expect("Failed to load OBJ file");
-
-    for (_i, m) in models.iter().enumerate() {
-        let mesh = &m.mesh;
-
-        let mut vertices: Vec<Point3> = Vec::default();
-        for v in 0..mesh.positions.len() / 3 {
-            let x = mesh.positions[3 * v] as f64;
-            let y = mesh.positions[3 * v + 1] as f64;
-            let z = mesh.positions[3 * v + 2] as f64;
-            vertices.push(Point3::new(x, y, z));
-        }
-        let mut object = HittableList::default();
-
-        for v in 0..mesh.indices.len() / 3 {
-            let x = mesh.indices[v * 3] as usize;
-            let y = mesh.indices[v * 3 + 1] as usize;
-            let z = mesh.indices[v * 3 + 2] as usize;
-
-            let tri = Triangle::new(
-                vertices[x],
-                vertices[y],
-                vertices[z],
-                // Lambertian::new_arc(tex_ptr),
-                Lambertian::new(Color::new(0.78, 0.78, 0.78)),
-            );
-            object.add(Arc::new(tri));
-        }
-
-        let object = BvhNode::new_list(&object, 0., 1.);
-        let object = Zoom::new(object, 13.5);
-        let object = RotateY::new(object, 56.);
-        let object = Translate::new(object, Vec3::new(40.88, 1.3, -85.59));
-        world.add(Arc::new(object));
+fn get_shuttle(world: &mut HittableList) -> Result<(), ImportError> {
+    let (objects, warning) = load_obj("source/obj/Shuttle.obj")?;
+    if let Some(e) = warning {
+        println!(
+            "{}",
+            style(format!(
+                "Loading shuttle materials fails, using the default: {}",
+                e
+            ))
+            .red()
+        );
     }
+    for object in objects {
+        let transform = Transform::translate(Vec3::new(40.88, 1.3, -85.59))
+            * Transform::rotate(Vec3::new(0., 1., 0.), 56.)
+            * Transform::scale(Vec3::new(13.5, 13.5, 13.5));
+        world.add(Arc::new(Instance::new(object.mesh, transform)));
+    }
+    Ok(())
 }
 
-fn get_ship(world: &mut HittableList) {
-    let file_path = "source/obj/";
-    let file_name = file_path.to_string() + "Ship.obj";
-
-    let obj = tobj::load_obj(
-        file_name,
-        &tobj::LoadOptions {
-            single_index: true,
-            triangulate: true,
-            ..Default::default()
-        },
-    );
-
-    assert!(obj.is_ok());
-
-    let (models, _materials) = obj.expect("Failed to load OBJ file");
-
-    for (_i, m) in models.iter().enumerate() {
-        let mesh = &m.mesh;
-
-        let mut vertices: Vec<Point3> = Vec::default();
-        for v in 0..mesh.positions.len() / 3 {
-            let x = mesh.positions[3 * v] as f64;
-            let y = mesh.positions[3 * v + 1] as f64;
-            let z = mesh.positions[3 * v + 2] as f64;
-            vertices.push(Point3::new(x, y, z));
-        }
-        let mut object = HittableList::default();
-
-        for v in 0..mesh.indices.len() / 3 {
-            let x = mesh.indices[v * 3] as usize;
-            let y = mesh.indices[v * 3 + 1] as usize;
-            let z = mesh.indices[v * 3 + 2] as usize;
-
-            let tri = Triangle::new(
-                vertices[x],
-                vertices[y],
-                vertices[z],
-                Lambertian::new(Color::new(0.78, 0.78, 0.78)),
-            );
-            object.add(Arc::new(tri));
-        }
-
-        let object = BvhNode::new_list(&object, 0., 1.);
-        let object = Zoom::new(object, 0.56);
-        let object = RotateY::new(object, 153.);
-        let object = Translate::new(object, Vec3::new(15., 2., -116.));
-        world.add(Arc::new(object));
+fn get_ship(world: &mut HittableList) -> Result<(), ImportError> {
+    let (objects, warning) = load_obj("source/obj/Ship.obj")?;
+    if let Some(e) = warning {
+        println!(
+            "{}",
+            style(format!(
+                "Loading ship materials fails, using the default: {}",
+                e
+            ))
+            .red()
+        );
     }
+    for object in objects {
+        let transform = Transform::translate(Vec3::new(15., 2., -116.))
+            * Transform::rotate(Vec3::new(0., 1., 0.), 153.)
+            * Transform::scale(Vec3::new(0.56, 0.56, 0.56));
+        world.add(Arc::new(Instance::new(object.mesh, transform)));
+    }
+    Ok(())
 }
 
 #[allow(dead_code)]
-pub fn wwscene() -> (HittableList, HittableList) {
+pub fn wwscene() -> (HittableList, HittableList, LightList) {
     let mut world = HittableList::default();
     let mut lights = HittableList::default();
 
     // Lights
-    let light_strong = DiffuseLight::new(Color::new(130., 130., 130.));
+    let light_strong = DiffuseLight::new_power(SolidColor::new(Color::new(1., 1., 1.)), 130.);
     let light_sphere = Sphere::new(Point3::new(800., 700., -800.), 70., light_strong);
     world.add(Arc::new(light_sphere.clone()));
     lights.add(Arc::new(light_sphere));
@@ -502,21 +1028,28 @@ pub fn wwscene() -> (HittableList, HittableList) {
         mars_surface,
     )));
 
-    // ring star
+    // ring star, all instances of one unit sphere
     let mut rng = rand::thread_rng();
-    for _i in 0..40 {
-        let mut pos = random_in_unit_xz_disk().to_unit() * (100. + rng.gen_range(-15.0..=15.0));
-        pos += Vec3::new(0., 0., rng.gen_range(-1.0..=1.0));
-        let albedo = Color::random(0.5, 1.);
-        let fuzz = rng.gen_range(0.0..0.5);
-        let ring_star = Sphere::new(pos, rng.gen_range(0.3..=0.5), Metal::new(albedo, fuzz));
-        world.add(Arc::new(ring_star));
-    }
-    for _i in 0..40 {
+    let ring_star: Arc<dyn Hittable> = Arc::new(Sphere::new(
+        Point3::new(0., 0., 0.),
+        1.,
+        Dielectric::new(1.5),
+    ));
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    for i in 0..80 {
         let mut pos = random_in_unit_xz_disk().to_unit() * (100. + rng.gen_range(-15.0..=15.0));
         pos += Vec3::new(0., 0., rng.gen_range(-1.0..=1.0));
-        let ring_star = Sphere::new(pos, rng.gen_range(0.3..=0.6), Dielectric::new(1.5));
-        world.add(Arc::new(ring_star));
+        let (radius, material): (f64, Arc<dyn Material>) = if i < 40 {
+            let albedo = Color::random(0.5, 1.);
+            let fuzz = rng.gen_range(0.0..0.5);
+            (rng.gen_range(0.3..=0.5), Arc::new(Metal::new(albedo, fuzz)))
+        } else {
+            (rng.gen_range(0.3..=0.6), glass.clone())
+        };
+        let transform =
+            Transform::translate(pos) * Transform::scale(Vec3::new(radius, radius, radius));
+        let instance = Instance::new(ring_star.clone(), transform).override_material(material);
+        world.add(Arc::new(instance));
     }
 
     // ring
@@ -560,12 +1093,18 @@ pub fn wwscene() -> (HittableList, HittableList) {
             rng.gen_range(0.3..=0.45),
             DiffuseLight::new(scolor),
         );
-        world.add(Arc::new(star))
+        let star = Arc::new(star);
+        world.add(star.clone());
+        lights.add(star);
     }
 
     // Import Object
-    get_shuttle(&mut world);
-    get_ship(&mut world);
+    if let Err(e) = get_shuttle(&mut world) {
+        println!("{}", style(format!("Loading shuttle fails: {}", e)).red());
+    }
+    if let Err(e) = get_ship(&mut world) {
+        println!("{}", style(format!("Loading ship fails: {}", e)).red());
+    }
 
-    (world, lights)
+    (world, lights, LightList::default())
 }
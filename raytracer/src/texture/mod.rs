@@ -5,10 +5,24 @@ use std::sync::Arc;
 use image::{GenericImageView, RgbImage};
 
 use crate::basic::vec::{Color, Point3};
+use crate::hittable::HitRecord;
 use crate::texture::perlin::Perlin;
 
 pub trait Texture: Send + Sync {
     fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+    // Textures which need more than the uv and point of a hit look at the whole record.
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.value(rec.u, rec.v, rec.p)
+    }
+}
+
+impl Texture for Arc<dyn Texture> {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        self.as_ref().value(u, v, p)
+    }
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        self.as_ref().value_at(rec)
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -28,6 +42,19 @@ impl Texture for SolidColor {
     }
 }
 
+// Colors stored with the vertices of a mesh, white for other surfaces.
+#[derive(Clone, Copy)]
+pub struct VertexColor;
+
+impl Texture for VertexColor {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        Color::new(1., 1., 1.)
+    }
+    fn value_at(&self, rec: &HitRecord) -> Color {
+        rec.color
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct CheckerTexture<TO, TE>
 where
@@ -87,16 +114,23 @@ pub struct ImageTexture {
 
 impl ImageTexture {
     pub fn new(filename: &str) -> Self {
-        let img = image::open(filename).unwrap();
+        Self::open(filename).unwrap()
+    }
+
+    pub fn open(filename: &str) -> image::ImageResult<Self> {
+        let img = image::open(filename)?;
         let (width, height) = img.dimensions();
+        let pixels = img.to_rgb8().pixels().map(|p| p.0).collect();
+        Ok(Self::new_pixels(width, height, pixels))
+    }
+
+    // `pixels` are given row by row from the top of the image.
+    pub fn new_pixels(width: u32, height: u32, pixels: Vec<[u8; 3]>) -> Self {
         let mut pixel_color: Vec<[u8; 3]> = Default::default();
 
         for y in 0..height {
-            for x in 0..width {
-                let pixel = img.get_pixel(x, height - y - 1);
-                let tmp = [pixel[0], pixel[1], pixel[2]];
-                pixel_color.push(tmp);
-            }
+            let row = (height - y - 1) as usize * width as usize;
+            pixel_color.extend_from_slice(&pixels[row..row + width as usize]);
         }
 
         Self {